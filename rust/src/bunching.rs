@@ -1,3 +1,4 @@
+use crate::cards::reject_dead_cards;
 use crate::range::*;
 
 use postflop_solver::*;
@@ -7,13 +8,16 @@ pub fn bunching_init(
     range_state: &RangeManager,
     bunching_state: &mut Option<BunchingData>,
     board: Vec<u8>,
+    dead_cards: Vec<u8>,
 ) -> Option<String> {
     if board.len() < 3 {
         return Some("Board must have at least 3 cards".to_string());
     }
+    if let Err(e) = reject_dead_cards(&dead_cards) {
+        return Some(e);
+    }
 
-    let ranges = &range_state.0;
-    let bunching_data = BunchingData::new(&ranges[2..], board[..3].try_into().unwrap());
+    let bunching_data = BunchingData::new(&range_state.0[2..], board[..3].try_into().unwrap());
 
     match bunching_data {
         Ok(bunching_data) => {
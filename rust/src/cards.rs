@@ -0,0 +1,266 @@
+use postflop_solver::*;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Parses cards written as `AsKd7c`. Spaces and commas between cards are ignored.
+pub fn parse_cards(str: &str) -> Result<Vec<Card>, String> {
+    let chars = str
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .collect::<Vec<_>>();
+    if chars.len() % 2 != 0 {
+        return Err(format!("Invalid card notation: {str}"));
+    }
+
+    let cards = chars
+        .chunks(2)
+        .map(|c| card_from_str(&c.iter().collect::<String>()))
+        .collect::<Result<Vec<_>, _>>()?;
+    validate_cards(&cards)?;
+    Ok(cards)
+}
+
+/// Parses a hole-card combo such as `AhKh`, returned in the order used by `private_cards`.
+pub fn parse_hand(str: &str) -> Result<(Card, Card), String> {
+    match parse_cards(str)?.as_slice() {
+        &[c1, c2] => Ok((c1.min(c2), c1.max(c2))),
+        _ => Err(format!("Invalid hand: {str}")),
+    }
+}
+
+pub fn validate_cards(cards: &[Card]) -> Result<(), String> {
+    let mut seen = 0u64;
+    for &card in cards {
+        if card >= 52 {
+            return Err(format!("Invalid card: {card}"));
+        }
+        if seen & (1 << card) != 0 {
+            return Err(format!("Duplicate card: {}", card_to_string(card).unwrap()));
+        }
+        seen |= 1 << card;
+    }
+    Ok(())
+}
+
+pub fn cards_to_string(cards: &[Card]) -> String {
+    cards.iter().map(|&c| card_to_string(c).unwrap()).collect()
+}
+
+pub fn cards_to_mask(cards: &[Card]) -> u64 {
+    cards.iter().fold(0, |mask, &card| mask | (1 << card))
+}
+
+/// The solver deals every card that is not on the board and cannot take cards out of the deck, so
+/// dead cards are refused rather than only removed from the ranges.
+pub fn reject_dead_cards(dead_cards: &[Card]) -> Result<(), String> {
+    match dead_cards.is_empty() {
+        true => Ok(()),
+        false => Err("Dead cards are not supported by the solver".to_string()),
+    }
+}
+
+/// Strength of the best five-card hand among `cards`; higher is better. The category, from 0 for a
+/// high card up to 8 for a straight flush, is stored from bit 20 and the kickers below it.
+pub fn hand_strength(cards: u64) -> u32 {
+    let mut rank_count = [0u8; 13];
+    let mut suit_ranks = [0u16; 4];
+    let mut ranks = 0u16;
+    for card in (0..52).filter(|&card| cards & (1 << card) != 0) {
+        rank_count[card >> 2] += 1;
+        suit_ranks[card & 3] |= 1 << (card >> 2);
+        ranks |= 1 << (card >> 2);
+    }
+
+    // top card of the best straight, with the wheel topping at 0
+    let straight = |mask: u16| {
+        let mask = (mask << 1) | (mask >> 12);
+        let tops = mask & (mask >> 1) & (mask >> 2) & (mask >> 3) & (mask >> 4);
+        (tops != 0).then(|| 15 - tops.leading_zeros())
+    };
+    // the `n` highest ranks of `mask`, most significant first
+    let top = |mask: u16, n: usize| {
+        (0..13)
+            .rev()
+            .filter(|&rank| mask & (1 << rank) != 0)
+            .take(n)
+            .fold(0, |acc, rank| acc << 4 | rank)
+    };
+    let with_count = |count: u8| {
+        (0..13)
+            .filter(|&rank| rank_count[rank] >= count)
+            .fold(0u16, |mask, rank| mask | 1 << rank)
+    };
+    let category = |category: u32, kickers: u32| category << 20 | kickers;
+
+    let flush = suit_ranks.iter().find(|mask| mask.count_ones() >= 5);
+    let (quads, trips, pairs) = (with_count(4), with_count(3), with_count(2));
+
+    if let Some(high) = flush.and_then(|&mask| straight(mask)) {
+        category(8, high)
+    } else if quads != 0 {
+        let rank = top(quads, 1);
+        category(7, rank << 4 | top(ranks & !(1 << rank), 1))
+    } else if trips != 0 && (pairs & !(1 << top(trips, 1))) != 0 {
+        let rank = top(trips, 1);
+        category(6, rank << 4 | top(pairs & !(1 << rank), 1))
+    } else if let Some(&mask) = flush {
+        category(5, top(mask, 5))
+    } else if let Some(high) = straight(ranks) {
+        category(4, high)
+    } else if trips != 0 {
+        let rank = top(trips, 1);
+        category(3, rank << 8 | top(ranks & !(1 << rank), 2))
+    } else if pairs.count_ones() >= 2 {
+        let two = top(pairs, 2);
+        let kicker = top(ranks & !(1 << (two >> 4)) & !(1 << (two & 15)), 1);
+        category(2, two << 4 | kicker)
+    } else if pairs != 0 {
+        let rank = top(pairs, 1);
+        category(1, rank << 12 | top(ranks & !(1 << rank), 3))
+    } else {
+        category(0, top(ranks, 5))
+    }
+}
+
+pub fn mask_to_strings(mask: u64) -> Vec<String> {
+    (0..52)
+        .filter(|&card| mask & (1 << card) != 0)
+        .map(|card| card_to_string(card).unwrap())
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CardsValue {
+    Ids(Vec<Card>),
+    Notation(String),
+}
+
+/// Accepts either card ids (`[51, 46, 21]`) or notation (`"AsKd7c"`).
+pub fn deserialize_cards<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Card>, D::Error> {
+    match CardsValue::deserialize(deserializer)? {
+        CardsValue::Ids(cards) => validate_cards(&cards).map(|_| cards),
+        CardsValue::Notation(str) => parse_cards(&str),
+    }
+    .map_err(D::Error::custom)
+}
+
+/// An entry of a history: an action index, or the card dealt at a chance node.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HistoryValue<T> {
+    Index(T),
+    Card(String),
+}
+
+/// Accepts the chance cards of a history as notation (`[0, 1, "Td", 2]`) as well as ids.
+pub fn deserialize_history<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + From<Card>,
+{
+    Vec::<HistoryValue<T>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|value| match value {
+            HistoryValue::Index(index) => Ok(index),
+            HistoryValue::Card(str) => card_from_str(&str).map(T::from).map_err(D::Error::custom),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strength(str: &str) -> u32 {
+        hand_strength(cards_to_mask(&parse_cards(str).unwrap()))
+    }
+
+    #[test]
+    fn hand_strength_ranks_categories() {
+        let ordered = [
+            "AsKd9h7c5d3s2h", // high card
+            "AsAd9h7c5d3s2h", // pair
+            "AsAd9h9c5d3s2h", // two pair
+            "AsAd9h9c6d3s2h", // two pair, better kicker
+            "AsAdAh7c5d3s2h", // trips
+            "As2d3h4c5d9s9h", // wheel
+            "6s2d3h4c5d9s9h", // six-high straight
+            "As9s7s5s2sKdKh", // flush
+            "AsAdAh7c7d3s2h", // full house
+            "AsAdAhAc7d3s2h", // quads
+            "As2s3s4s5sKdKh", // straight flush
+        ];
+        for pair in ordered.windows(2) {
+            assert!(strength(pair[0]) < strength(pair[1]), "{pair:?}");
+        }
+        assert_eq!(strength("AsKd9h7c5d3s2h"), strength("AhKc9d7s5c3h2d"));
+        // only the best five cards count
+        assert_eq!(strength("AsAd9h9c5d5s2h"), strength("AsAd9h9c5d3s2h"));
+    }
+
+    #[test]
+    fn parse_cards_round_trips() {
+        let cards = parse_cards("As Kd, 7c").unwrap();
+        assert_eq!(cards, [51, 45, 20]);
+        assert_eq!(cards_to_string(&cards), "AsKd7c");
+        assert_eq!(mask_to_strings(cards_to_mask(&cards)), ["7c", "Kd", "As"]);
+        assert!(parse_cards("").unwrap().is_empty());
+
+        assert_eq!(parse_hand("KhAh").unwrap(), (46, 50));
+        assert!(parse_hand("AhKhQh").is_err());
+    }
+
+    #[test]
+    fn parse_cards_rejects_bad_input() {
+        assert!(parse_cards("AsK").is_err());
+        assert!(parse_cards("1s").is_err());
+        assert!(parse_cards("Ax").is_err());
+        assert_eq!(parse_cards("AsKdAs"), Err("Duplicate card: As".to_string()));
+        assert_eq!(
+            validate_cards(&[51, 52]),
+            Err("Invalid card: 52".to_string())
+        );
+        assert!(validate_cards(&[0, 51]).is_ok());
+    }
+
+    #[derive(Deserialize)]
+    struct Board {
+        #[serde(deserialize_with = "deserialize_cards")]
+        cards: Vec<Card>,
+    }
+
+    #[derive(Deserialize)]
+    struct History {
+        #[serde(deserialize_with = "deserialize_history")]
+        history: Vec<isize>,
+    }
+
+    #[test]
+    fn deserialize_cards_accepts_ids_or_notation() {
+        let parse = |json| serde_json::from_str::<Board>(json).map(|board| board.cards);
+        assert_eq!(parse(r#"{ "cards": [51, 45, 20] }"#).unwrap(), [51, 45, 20]);
+        assert_eq!(parse(r#"{ "cards": "AsKd7c" }"#).unwrap(), [51, 45, 20]);
+        assert!(parse(r#"{ "cards": [51, "Kd"] }"#).is_err());
+        assert!(parse(r#"{ "cards": [51, 51] }"#).is_err());
+        assert!(parse(r#"{ "cards": [52] }"#).is_err());
+        assert!(parse(r#"{ "cards": "AsAs" }"#).is_err());
+    }
+
+    #[test]
+    fn deserialize_history_accepts_card_notation() {
+        let parse = |json| serde_json::from_str::<History>(json).map(|h| h.history);
+        assert_eq!(
+            parse(r#"{ "history": [0, 1, "Td", 2] }"#).unwrap(),
+            [0, 1, 33, 2]
+        );
+        assert_eq!(parse(r#"{ "history": [-1, 33] }"#).unwrap(), [-1, 33]);
+        assert!(parse(r#"{ "history": [0, "Tx"] }"#).is_err());
+        assert!(parse(r#"{ "history": [0, 1.5] }"#).is_err());
+
+        let history = [HistoryValue::Index(0), HistoryValue::Card("Td".to_string())];
+        assert_eq!(serde_json::to_string(&history).unwrap(), r#"[0,"Td"]"#);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::cards::{cards_to_mask, hand_strength};
+use crate::export::*;
+use crate::state::SessionState;
+use crate::tree::Street;
+
+use arrow_array::builder::{Float64Builder, Int32Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use postflop_solver::*;
+use serde::Deserialize;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+
+const BATCH_SIZE: usize = 1 << 16;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnarFormat {
+    Arrow,
+    Parquet,
+}
+
+impl ColumnarFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ColumnarFormat::Arrow => "application/vnd.apache.arrow.file",
+            ColumnarFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+}
+
+/// Passes everything written to `send`, failing once `send` returns `false`.
+struct SendWriter<'a>(&'a mut (dyn FnMut(Vec<u8>) -> bool + Send));
+
+impl Write for SendWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match (self.0)(buf.to_vec()) {
+            true => Ok(buf.len()),
+            false => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+type ChunkWriter<'a> = BufWriter<SendWriter<'a>>;
+
+enum Writer<'a> {
+    Arrow(FileWriter<ChunkWriter<'a>>),
+    Parquet(ArrowWriter<ChunkWriter<'a>>),
+}
+
+impl<'a> Writer<'a> {
+    fn new(
+        format: ColumnarFormat,
+        schema: SchemaRef,
+        send: &'a mut (dyn FnMut(Vec<u8>) -> bool + Send),
+    ) -> Result<Self, String> {
+        let out = BufWriter::with_capacity(CHUNK_SIZE, SendWriter(send));
+        match format {
+            ColumnarFormat::Arrow => FileWriter::try_new(out, &schema)
+                .map(Writer::Arrow)
+                .map_err(|e| e.to_string()),
+            ColumnarFormat::Parquet => ArrowWriter::try_new(out, schema, None)
+                .map(Writer::Parquet)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), String> {
+        match self {
+            Writer::Arrow(w) => w.write(batch).map_err(|e| e.to_string()),
+            Writer::Parquet(w) => w.write(batch).map_err(|e| e.to_string()),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        let out = match self {
+            Writer::Arrow(mut w) => {
+                w.finish().map_err(|e| e.to_string())?;
+                w.into_inner().map_err(|e| e.to_string())?
+            }
+            Writer::Parquet(w) => w.into_inner().map_err(|e| e.to_string())?,
+        };
+        out.into_inner()
+            .map(drop)
+            .map_err(|e| e.error().to_string())
+    }
+}
+
+#[derive(Default)]
+struct Columns {
+    len: usize,
+    board: StringBuilder,
+    line: StringBuilder,
+    street: StringBuilder,
+    pot: Int32Builder,
+    player: StringBuilder,
+    hand: StringBuilder,
+    hand_category: StringBuilder,
+    weight: Float64Builder,
+    equity: Float64Builder,
+    ev: Float64Builder,
+    action: StringBuilder,
+    frequency: Float64Builder,
+    action_ev: Float64Builder,
+}
+
+impl Columns {
+    fn schema() -> SchemaRef {
+        let utf8 = |name| Field::new(name, DataType::Utf8, false);
+        let f64 = |name, nullable| Field::new(name, DataType::Float64, nullable);
+        Arc::new(Schema::new(vec![
+            utf8("board"),
+            utf8("line"),
+            utf8("street"),
+            Field::new("pot", DataType::Int32, false),
+            utf8("player"),
+            utf8("hand"),
+            utf8("hand_category"),
+            f64("weight", false),
+            f64("equity", true),
+            f64("ev", true),
+            utf8("action"),
+            f64("frequency", false),
+            f64("action_ev", true),
+        ]))
+    }
+
+    fn push(&mut self, node: &ExportNode, categories: &[&str]) {
+        let num_hands = node.hands.len();
+        for (hand_index, hand) in node.hands.iter().enumerate() {
+            for (action_index, action) in node.actions.iter().enumerate() {
+                let index = action_index * num_hands + hand_index;
+                self.board.append_value(&node.board);
+                self.line.append_value(&node.line);
+                self.street.append_value(node.street);
+                self.pot.append_value(node.pot);
+                self.player.append_value(node.player);
+                self.hand.append_value(hand);
+                self.hand_category.append_value(categories[hand_index]);
+                self.weight.append_value(node.weights[hand_index]);
+                self.equity
+                    .append_option(node.equity.get(hand_index).copied());
+                self.ev.append_option(node.ev.get(hand_index).copied());
+                self.action.append_value(action);
+                self.frequency.append_value(node.strategy[index]);
+                self.action_ev
+                    .append_option(node.action_ev.get(index).copied());
+                self.len += 1;
+            }
+        }
+    }
+
+    fn finish(&mut self, schema: &SchemaRef) -> Result<RecordBatch, String> {
+        self.len = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.board.finish()),
+            Arc::new(self.line.finish()),
+            Arc::new(self.street.finish()),
+            Arc::new(self.pot.finish()),
+            Arc::new(self.player.finish()),
+            Arc::new(self.hand.finish()),
+            Arc::new(self.hand_category.finish()),
+            Arc::new(self.weight.finish()),
+            Arc::new(self.equity.finish()),
+            Arc::new(self.ev.finish()),
+            Arc::new(self.action.finish()),
+            Arc::new(self.frequency.finish()),
+            Arc::new(self.action_ev.finish()),
+        ];
+        RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())
+    }
+}
+
+const HAND_CATEGORIES: [&str; 9] = [
+    "high_card",
+    "pair",
+    "two_pair",
+    "trips",
+    "straight",
+    "flush",
+    "full_house",
+    "quads",
+    "straight_flush",
+];
+
+/// Returns the made-hand category of `hand` on `board`.
+pub fn hand_category(hand: (Card, Card), board: &[Card]) -> &'static str {
+    let cards = cards_to_mask(board) | 1 << hand.0 | 1 << hand.1;
+    HAND_CATEGORIES[(hand_strength(cards) >> 20) as usize]
+}
+
+/// Writes the export of every decision node as an Arrow or Parquet file, passing the bytes to
+/// `send` as they are produced.
+pub fn game_export_columnar(
+    state: &SessionState,
+    format: ColumnarFormat,
+    street: Option<Street>,
+    max_depth: Option<usize>,
+    send: &mut (dyn FnMut(Vec<u8>) -> bool + Send),
+) -> Result<(), String> {
+    let mut nodes = LockedDecisionNodes::new(state, street, max_depth)?;
+    let schema = Columns::schema();
+    let mut writer = Writer::new(format, schema.clone(), send)?;
+    let mut columns = Columns::default();
+
+    while let Some((node, categories)) = nodes.next(|game, line| {
+        let node = export_node(game, line);
+        let board = game.current_board();
+        let categories = game
+            .private_cards(game.current_player())
+            .iter()
+            .map(|&hand| hand_category(hand, &board))
+            .collect::<Vec<_>>();
+        (node, categories)
+    })? {
+        columns.push(&node, &categories);
+        if columns.len >= BATCH_SIZE {
+            writer.write(&columns.finish(&schema)?)?;
+        }
+    }
+
+    if columns.len > 0 {
+        writer.write(&columns.finish(&schema)?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{parse_cards, parse_hand};
+
+    fn category(hand: &str, board: &str) -> &'static str {
+        hand_category(parse_hand(hand).unwrap(), &parse_cards(board).unwrap())
+    }
+
+    #[test]
+    fn hand_category_of_each_made_hand() {
+        assert_eq!(category("AhKh", "Qd7c2s"), "high_card");
+        assert_eq!(category("Ah7h", "Qd7c2s"), "pair");
+        // a paired board counts for the hand
+        assert_eq!(category("AhKh", "Qd7c7s"), "pair");
+        assert_eq!(category("Qh7h", "Qd7c2s"), "two_pair");
+        assert_eq!(category("7h7d", "Qd7c2s"), "trips");
+        assert_eq!(category("5h4h", "3d2cAs"), "straight");
+        assert_eq!(category("AhKh", "Qh7h2h"), "flush");
+        assert_eq!(category("7h7d", "Qd7cQs"), "full_house");
+        assert_eq!(category("7h7d", "7c7s2s"), "quads");
+        assert_eq!(category("5h4h", "3h2hAh"), "straight_flush");
+    }
+
+    #[test]
+    fn hand_category_uses_the_turn_and_river() {
+        assert_eq!(category("AhKh", "Qh7h2c3h"), "flush");
+        assert_eq!(category("9h8d", "Td7c2sJs6h"), "straight");
+        assert_eq!(category("Ah2c", "KdKcKhQsQd"), "full_house");
+    }
+}
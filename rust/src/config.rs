@@ -0,0 +1,638 @@
+use crate::cards::{deserialize_cards, reject_dead_cards};
+use crate::tree::{apply_tree_options, decode_line};
+
+use postflop_solver::*;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+pub const CONFIG_VERSION: u64 = 2;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BetSizes {
+    pub bet: String,
+    pub raise: String,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreetSizes {
+    pub flop: BetSizes,
+    pub turn: BetSizes,
+    pub river: BetSizes,
+}
+
+/// OOP donk sizes, enabled separately on the turn and the river.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DonkSizes {
+    pub turn_enabled: bool,
+    pub turn: String,
+    pub river_enabled: bool,
+    pub river: String,
+}
+
+/// Everything needed to build the action tree and the game. `sizes` is indexed by player
+/// (OOP, IP).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameConfig {
+    pub version: u64,
+    #[serde(default, deserialize_with = "deserialize_cards")]
+    pub board: Vec<u8>,
+    /// Cards known to be out of play. Not supported by the solver, so they must be empty.
+    #[serde(default, deserialize_with = "deserialize_cards")]
+    pub dead_cards: Vec<u8>,
+    /// Used by the tree endpoints when the board is not given.
+    #[serde(default)]
+    pub expected_board_length: usize,
+    pub starting_pot: i32,
+    pub effective_stack: i32,
+    #[serde(default)]
+    pub rake_rate: f64,
+    #[serde(default)]
+    pub rake_cap: f64,
+    pub sizes: [StreetSizes; 2],
+    pub donk: DonkSizes,
+    pub add_allin_threshold: f64,
+    pub force_allin_threshold: f64,
+    pub merging_threshold: f64,
+    /// Tree-building options, applied on top of the edited lines.
+    #[serde(default)]
+    pub max_raises: Option<usize>,
+    #[serde(default)]
+    pub bet_unit: i32,
+    #[serde(default)]
+    pub added_lines: String,
+    #[serde(default)]
+    pub removed_lines: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DonkSizes1 {
+    enabled: bool,
+    turn: String,
+    river: String,
+}
+
+/// Version 1 had a single toggle for turn and river donk sizes.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameConfig1 {
+    #[serde(default, deserialize_with = "deserialize_cards")]
+    board: Vec<u8>,
+    #[serde(default)]
+    expected_board_length: usize,
+    starting_pot: i32,
+    effective_stack: i32,
+    #[serde(default)]
+    rake_rate: f64,
+    #[serde(default)]
+    rake_cap: f64,
+    sizes: [StreetSizes; 2],
+    donk: DonkSizes1,
+    add_allin_threshold: f64,
+    force_allin_threshold: f64,
+    merging_threshold: f64,
+    #[serde(default)]
+    max_raises: Option<usize>,
+    #[serde(default)]
+    bet_unit: i32,
+    #[serde(default)]
+    added_lines: String,
+    #[serde(default)]
+    removed_lines: String,
+}
+
+/// Flat shape of the `tree_new` and `game_init` requests before the configuration was versioned.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameConfig0 {
+    #[serde(default, deserialize_with = "deserialize_cards")]
+    board: Vec<u8>,
+    #[serde(default)]
+    board_len: usize,
+    starting_pot: i32,
+    effective_stack: i32,
+    #[serde(default)]
+    rake_rate: f64,
+    #[serde(default)]
+    rake_cap: f64,
+    donk_option: bool,
+    oop_flop_bet: String,
+    oop_flop_raise: String,
+    oop_turn_bet: String,
+    oop_turn_raise: String,
+    oop_turn_donk: String,
+    oop_river_bet: String,
+    oop_river_raise: String,
+    oop_river_donk: String,
+    ip_flop_bet: String,
+    ip_flop_raise: String,
+    ip_turn_bet: String,
+    ip_turn_raise: String,
+    ip_river_bet: String,
+    ip_river_raise: String,
+    add_allin_threshold: f64,
+    force_allin_threshold: f64,
+    merging_threshold: f64,
+    #[serde(default)]
+    max_raises: Option<usize>,
+    #[serde(default)]
+    bet_unit: i32,
+    added_lines: String,
+    removed_lines: String,
+}
+
+fn migrate_config_0_to_1(value: GameConfig0) -> GameConfig1 {
+    let sizes = |bet: String, raise: String| BetSizes { bet, raise };
+    GameConfig1 {
+        board: value.board,
+        expected_board_length: value.board_len,
+        starting_pot: value.starting_pot,
+        effective_stack: value.effective_stack,
+        rake_rate: value.rake_rate,
+        rake_cap: value.rake_cap,
+        sizes: [
+            StreetSizes {
+                flop: sizes(value.oop_flop_bet, value.oop_flop_raise),
+                turn: sizes(value.oop_turn_bet, value.oop_turn_raise),
+                river: sizes(value.oop_river_bet, value.oop_river_raise),
+            },
+            StreetSizes {
+                flop: sizes(value.ip_flop_bet, value.ip_flop_raise),
+                turn: sizes(value.ip_turn_bet, value.ip_turn_raise),
+                river: sizes(value.ip_river_bet, value.ip_river_raise),
+            },
+        ],
+        donk: DonkSizes1 {
+            enabled: value.donk_option,
+            turn: value.oop_turn_donk,
+            river: value.oop_river_donk,
+        },
+        add_allin_threshold: value.add_allin_threshold,
+        force_allin_threshold: value.force_allin_threshold,
+        merging_threshold: value.merging_threshold,
+        max_raises: value.max_raises,
+        bet_unit: value.bet_unit,
+        added_lines: value.added_lines,
+        removed_lines: value.removed_lines,
+    }
+}
+
+fn migrate_config_1_to_2(value: GameConfig1) -> GameConfig {
+    GameConfig {
+        version: 2,
+        board: value.board,
+        dead_cards: Vec::new(),
+        expected_board_length: value.expected_board_length,
+        starting_pot: value.starting_pot,
+        effective_stack: value.effective_stack,
+        rake_rate: value.rake_rate,
+        rake_cap: value.rake_cap,
+        sizes: value.sizes,
+        donk: DonkSizes {
+            turn_enabled: value.donk.enabled,
+            turn: value.donk.turn,
+            river_enabled: value.donk.enabled,
+            river: value.donk.river,
+        },
+        add_allin_threshold: value.add_allin_threshold,
+        force_allin_threshold: value.force_allin_threshold,
+        merging_threshold: value.merging_threshold,
+        max_raises: value.max_raises,
+        bet_unit: value.bet_unit,
+        added_lines: value.added_lines,
+        removed_lines: value.removed_lines,
+    }
+}
+
+/// A `GameConfig` deserialized from any version.
+pub struct VersionedConfig(pub GameConfig);
+
+impl<'de> Deserialize<'de> for VersionedConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        parse_config(value).map(Self).map_err(D::Error::custom)
+    }
+}
+
+/// Parses a configuration of any version, migrating it to the current one. A value without
+/// `version` is read as the flat request shape.
+pub fn parse_config(value: Value) -> Result<GameConfig, String> {
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    let parse_error = |e: serde_json::Error| format!("Invalid configuration ({e})");
+    match version {
+        0 => serde_json::from_value(value)
+            .map(migrate_config_0_to_1)
+            .map(migrate_config_1_to_2)
+            .map_err(parse_error),
+        1 => serde_json::from_value(value)
+            .map(migrate_config_1_to_2)
+            .map_err(parse_error),
+        CONFIG_VERSION => serde_json::from_value(value).map_err(parse_error),
+        _ => Err(format!("Unsupported configuration version: {version}")),
+    }
+}
+
+impl GameConfig {
+    fn board_state(&self) -> Result<BoardState, String> {
+        let board_len = match self.board.len() {
+            0 => self.expected_board_length,
+            len => len,
+        };
+        match board_len {
+            len if len <= 3 => Ok(BoardState::Flop),
+            4 => Ok(BoardState::Turn),
+            5 => Ok(BoardState::River),
+            _ => Err("Invalid board length".to_string()),
+        }
+    }
+
+    pub fn tree_config(&self) -> Result<TreeConfig, String> {
+        let sizes = |f: fn(&StreetSizes) -> &BetSizes| {
+            let [oop, ip] = [&self.sizes[0], &self.sizes[1]].map(|s| {
+                let sizes = f(s);
+                BetSizeOptions::try_from((sizes.bet.as_str(), sizes.raise.as_str()))
+            });
+            Ok::<_, String>([oop?, ip?])
+        };
+        let donk = |enabled: bool, str: &str| match enabled {
+            false => Ok(None),
+            true => DonkSizeOptions::try_from(str).map(Some),
+        };
+
+        Ok(TreeConfig {
+            initial_state: self.board_state()?,
+            starting_pot: self.starting_pot,
+            effective_stack: self.effective_stack,
+            rake_rate: self.rake_rate,
+            rake_cap: self.rake_cap,
+            flop_bet_sizes: sizes(|s| &s.flop)?,
+            turn_bet_sizes: sizes(|s| &s.turn)?,
+            river_bet_sizes: sizes(|s| &s.river)?,
+            turn_donk_sizes: donk(self.donk.turn_enabled, &self.donk.turn)?,
+            river_donk_sizes: donk(self.donk.river_enabled, &self.donk.river)?,
+            add_allin_threshold: self.add_allin_threshold,
+            force_allin_threshold: self.force_allin_threshold,
+            merging_threshold: self.merging_threshold,
+        })
+    }
+
+    /// Builds the action tree with the edited lines, without the tree-building options.
+    pub fn action_tree(&self) -> Result<ActionTree, String> {
+        let mut action_tree = ActionTree::new(self.tree_config()?)?;
+
+        if !self.added_lines.is_empty() {
+            for line in self.added_lines.split(',') {
+                if action_tree.add_line(&decode_line(line)?).is_err() {
+                    return Err("Failed to add line (loaded broken tree?)".to_string());
+                }
+            }
+        }
+
+        if !self.removed_lines.is_empty() {
+            for line in self.removed_lines.split(',') {
+                if action_tree.remove_line(&decode_line(line)?).is_err() {
+                    return Err("Failed to remove line (loaded broken tree?)".to_string());
+                }
+            }
+        }
+
+        Ok(action_tree)
+    }
+
+    /// Builds the tree that is edited and solved: the edited lines with the tree-building options
+    /// applied.
+    pub fn game_tree(&self) -> Result<ActionTree, String> {
+        let mut action_tree = self.action_tree()?;
+        apply_tree_options(&mut action_tree, self.max_raises, self.bet_unit)?;
+        Ok(action_tree)
+    }
+
+    pub fn card_config(&self, ranges: [Range; 2]) -> Result<CardConfig, String> {
+        let board = &self.board;
+        reject_dead_cards(&self.dead_cards)?;
+        let (turn, river) = match board.len() {
+            3 => (NOT_DEALT, NOT_DEALT),
+            4 => (board[3], NOT_DEALT),
+            5 => (board[3], board[4]),
+            _ => return Err("Invalid board length".to_string()),
+        };
+
+        Ok(CardConfig {
+            range: ranges,
+            flop: board[..3].try_into().unwrap(),
+            turn,
+            river,
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SizeKind {
+    Bet,
+    Raise,
+    Donk,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeToken {
+    position: usize,
+    token: String,
+    interpretation: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeDiagnostic {
+    field: String,
+    position: usize,
+    token: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SizeField {
+    field: String,
+    value: String,
+    tokens: Vec<SizeToken>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigValidation {
+    is_valid: bool,
+    diagnostics: Vec<SizeDiagnostic>,
+    fields: Vec<SizeField>,
+}
+
+fn parse_size(kind: SizeKind, token: &str) -> Result<BetSize, String> {
+    let sizes = match kind {
+        SizeKind::Bet => BetSizeOptions::try_from((token, "")).map(|o| o.bet),
+        SizeKind::Raise => BetSizeOptions::try_from(("", token)).map(|o| o.raise),
+        SizeKind::Donk => DonkSizeOptions::try_from(token).map(|o| o.donk),
+    }?;
+    match sizes[..] {
+        [size] => Ok(size),
+        _ => Err("Expected a single size".to_string()),
+    }
+}
+
+fn interpret_size(kind: SizeKind, size: BetSize) -> String {
+    let percent = |ratio: f64| format!("{}%", (ratio * 1e4).round() / 1e2);
+    match size {
+        BetSize::PotRelative(ratio) => format!("{} pot", percent(ratio)),
+        BetSize::PrevBetRelative(ratio) => match kind {
+            SizeKind::Raise => format!("{ratio}x raise"),
+            _ => format!("{ratio}x previous bet"),
+        },
+        BetSize::Additive(chips, 0) => format!("previous bet + {chips} chips"),
+        BetSize::Additive(chips, cap) => {
+            format!("previous bet + {chips} chips, up to {cap} raises")
+        }
+        BetSize::Geometric(streets, max_ratio) => {
+            let streets = match streets {
+                0 => "the remaining streets".to_string(),
+                1 => "1 street".to_string(),
+                n => format!("{n} streets"),
+            };
+            match max_ratio.is_finite() {
+                true => format!(
+                    "geometric over {streets}, at most {} pot",
+                    percent(max_ratio)
+                ),
+                false => format!("geometric over {streets}"),
+            }
+        }
+        BetSize::AllIn => "all-in".to_string(),
+    }
+}
+
+fn validate_field(
+    field: String,
+    value: &str,
+    kind: SizeKind,
+    diagnostics: &mut Vec<SizeDiagnostic>,
+) -> SizeField {
+    let mut tokens = Vec::new();
+    let mut position = 0;
+    let mut has_error = false;
+
+    if !value.trim().is_empty() {
+        for raw in value.split(',') {
+            let token = raw.trim();
+            let token_position = position + raw.len() - raw.trim_start().len();
+            position += raw.len() + 1;
+
+            let result = match token.is_empty() {
+                true => Err("Empty size".to_string()),
+                false => parse_size(kind, token),
+            };
+            match result {
+                Ok(size) => tokens.push(SizeToken {
+                    position: token_position,
+                    token: token.to_string(),
+                    interpretation: interpret_size(kind, size),
+                }),
+                Err(reason) => {
+                    has_error = true;
+                    diagnostics.push(SizeDiagnostic {
+                        field: field.clone(),
+                        position: token_position,
+                        token: token.to_string(),
+                        reason,
+                    });
+                }
+            }
+        }
+    }
+
+    // catches errors that depend on the combination of sizes
+    if !has_error {
+        let result = match kind {
+            SizeKind::Bet => BetSizeOptions::try_from((value, "")).map(drop),
+            SizeKind::Raise => BetSizeOptions::try_from(("", value)).map(drop),
+            SizeKind::Donk => DonkSizeOptions::try_from(value).map(drop),
+        };
+        if let Err(reason) = result {
+            diagnostics.push(SizeDiagnostic {
+                field: field.clone(),
+                position: 0,
+                token: value.to_string(),
+                reason,
+            });
+        }
+    }
+
+    SizeField {
+        field,
+        value: value.to_string(),
+        tokens,
+    }
+}
+
+/// Parses every size field of `config` token by token. Field names are `oop.flop.bet`,
+/// `ip.river.raise`, `donk.turn` and so on.
+pub fn config_validate(config: &GameConfig) -> ConfigValidation {
+    let mut diagnostics = Vec::new();
+    let mut fields = Vec::new();
+
+    for (player, sizes) in ["oop", "ip"].into_iter().zip(&config.sizes) {
+        let streets = [
+            ("flop", &sizes.flop),
+            ("turn", &sizes.turn),
+            ("river", &sizes.river),
+        ];
+        for (street, sizes) in streets {
+            for (name, value, kind) in [
+                ("bet", &sizes.bet, SizeKind::Bet),
+                ("raise", &sizes.raise, SizeKind::Raise),
+            ] {
+                let field = format!("{player}.{street}.{name}");
+                fields.push(validate_field(field, value, kind, &mut diagnostics));
+            }
+        }
+    }
+
+    let donks = [
+        ("turn", config.donk.turn_enabled, &config.donk.turn),
+        ("river", config.donk.river_enabled, &config.donk.river),
+    ];
+    for (street, enabled, value) in donks {
+        if enabled {
+            let field = format!("donk.{street}");
+            fields.push(validate_field(
+                field,
+                value,
+                SizeKind::Donk,
+                &mut diagnostics,
+            ));
+        }
+    }
+
+    ConfigValidation {
+        is_valid: diagnostics.is_empty(),
+        diagnostics,
+        fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config_1() -> Value {
+        let sizes = json!({
+            "flop": { "bet": "33%", "raise": "2.5x" },
+            "turn": { "bet": "75%", "raise": "3x" },
+            "river": { "bet": "100%", "raise": "a" },
+        });
+        json!({
+            "version": 1,
+            "board": "Ah7d2c",
+            "startingPot": 60,
+            "effectiveStack": 970,
+            "rakeRate": 0.05,
+            "rakeCap": 30.0,
+            "sizes": [sizes, sizes],
+            "donk": { "enabled": true, "turn": "50%", "river": "e" },
+            "addAllinThreshold": 1.5,
+            "forceAllinThreshold": 0.15,
+            "mergingThreshold": 0.1,
+            "maxRaises": 3,
+            "addedLines": "B20-R60",
+            "removedLines": "X-B20",
+        })
+    }
+
+    #[test]
+    fn migrate_flat_request() {
+        let value = json!({
+            "board": [51, 23, 0],
+            "startingPot": 60,
+            "effectiveStack": 970,
+            "donkOption": false,
+            "oopFlopBet": "33%",
+            "oopFlopRaise": "2.5x",
+            "oopTurnBet": "75%",
+            "oopTurnRaise": "3x",
+            "oopTurnDonk": "",
+            "oopRiverBet": "100%",
+            "oopRiverRaise": "a",
+            "oopRiverDonk": "",
+            "ipFlopBet": "50%",
+            "ipFlopRaise": "3x",
+            "ipTurnBet": "75%",
+            "ipTurnRaise": "3x",
+            "ipRiverBet": "e",
+            "ipRiverRaise": "a",
+            "addAllinThreshold": 1.5,
+            "forceAllinThreshold": 0.15,
+            "mergingThreshold": 0.1,
+            "addedLines": "",
+            "removedLines": "",
+        });
+        let config = parse_config(value).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.board, [51, 23, 0]);
+        assert_eq!(config.sizes[0].flop.bet, "33%");
+        assert_eq!(config.sizes[1].river.bet, "e");
+        assert!(!config.donk.turn_enabled && !config.donk.river_enabled);
+    }
+
+    #[test]
+    fn migrate_1_to_2_round_trip() {
+        let config = parse_config(config_1()).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(config.dead_cards.is_empty());
+        assert!(config.donk.turn_enabled && config.donk.river_enabled);
+        assert_eq!(
+            (config.donk.turn.as_str(), config.donk.river.as_str()),
+            ("50%", "e")
+        );
+        assert_eq!(config.max_raises, Some(3));
+
+        // the migrated config is saved as version 2 and reads back unchanged
+        let saved = serde_json::to_value(&config).unwrap();
+        let reloaded = parse_config(saved.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&reloaded).unwrap(), saved);
+    }
+
+    #[test]
+    fn parse_config_rejects_bad_input() {
+        let mut value = config_1();
+        value["version"] = json!(CONFIG_VERSION + 1);
+        assert!(parse_config(value).is_err());
+
+        let mut value = config_1();
+        value.as_object_mut().unwrap().remove("sizes");
+        assert!(parse_config(value).is_err());
+
+        let mut value = config_1();
+        value["board"] = json!("AhAh2c");
+        assert!(parse_config(value).is_err());
+    }
+
+    #[test]
+    fn card_config_rejects_dead_cards() {
+        let config = parse_config(config_1()).unwrap();
+        let card_config = config.card_config([Range::new(); 2]).unwrap();
+        assert_eq!(card_config.flop, [50, 21, 0]);
+        assert_eq!(
+            (card_config.turn, card_config.river),
+            (NOT_DEALT, NOT_DEALT)
+        );
+
+        let mut value = serde_json::to_value(&config).unwrap();
+        value["deadCards"] = json!("Kc");
+        let config = parse_config(value).unwrap();
+        assert_eq!(config.dead_cards, [44]);
+        assert!(config.card_config([Range::new(); 2]).is_err());
+    }
+}
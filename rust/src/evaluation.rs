@@ -0,0 +1,288 @@
+use crate::cards::{cards_to_string, parse_hand};
+use crate::range::hand_class;
+use crate::solver::{round, try_apply_history, with_locked_copy};
+use crate::state::MemoryBudget;
+use crate::tree::encode_action;
+
+use postflop_solver::*;
+use rayon::ThreadPool;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandEvaluation {
+    hand: String,
+    weight: f64,
+    strategy: Vec<f64>,
+    ev: f64,
+    user_ev: f64,
+    ev_loss: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyEvaluation {
+    player: usize,
+    actions: Vec<String>,
+    /// Range-weighted EVs at the node, assuming equilibrium play everywhere else.
+    ev: f64,
+    user_ev: f64,
+    ev_loss: f64,
+    /// Gain of an opponent best-responding to the evaluated strategy, in chips per hand at the root
+    /// like the exploitability.
+    best_response_ev_loss: f64,
+    hands: Vec<HandEvaluation>,
+}
+
+enum StrategyKey {
+    Combo((Card, Card)),
+    Class(String),
+}
+
+fn parse_key(key: &str) -> Result<StrategyKey, String> {
+    match key.len() {
+        4 => Ok(StrategyKey::Combo(parse_hand(key)?)),
+        2 | 3 => Ok(StrategyKey::Class(key.to_string())),
+        _ => Err(format!("Invalid hand: {key}")),
+    }
+}
+
+fn normalize(key: &str, freqs: &[f64], num_actions: usize) -> Result<Vec<f32>, String> {
+    if freqs.len() != num_actions {
+        return Err(format!(
+            "Expected {num_actions} frequencies for {key}, got {}",
+            freqs.len()
+        ));
+    }
+    let sum = freqs.iter().sum::<f64>();
+    if freqs.iter().any(|&f| !f.is_finite() || f < 0.0) || sum <= 0.0 {
+        return Err(format!("Invalid frequencies for {key}"));
+    }
+    Ok(freqs.iter().map(|&f| (f / sum) as f32).collect())
+}
+
+/// Builds the full strategy of the node from the equilibrium one, overriding the 13x13 cells and
+/// then the combos given in `overrides`.
+fn build_strategy(
+    game: &PostFlopGame,
+    overrides: &BTreeMap<String, Vec<f64>>,
+) -> Result<Vec<f32>, String> {
+    let player = game.current_player();
+    let cards = game.private_cards(player);
+    let num_hands = cards.len();
+    let num_actions = game.available_actions().len();
+    let mut strategy = game.strategy();
+
+    let mut classes = Vec::new();
+    let mut combos = Vec::new();
+    for (key, freqs) in overrides {
+        let freqs = normalize(key, freqs, num_actions)?;
+        match parse_key(key)? {
+            StrategyKey::Class(class) => classes.push((class, freqs)),
+            StrategyKey::Combo(combo) => combos.push((combo, freqs)),
+        }
+    }
+
+    let mut set = |index: usize, freqs: &[f32]| {
+        for (i, &f) in freqs.iter().enumerate() {
+            strategy[i * num_hands + index] = f;
+        }
+    };
+    for (class, freqs) in &classes {
+        let mut found = false;
+        for (index, &(c1, c2)) in cards.iter().enumerate() {
+            if hand_class(c1, c2) == *class {
+                set(index, freqs);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(format!("No combo of {class} in the range"));
+        }
+    }
+    for (combo, freqs) in &combos {
+        let index = cards
+            .iter()
+            .position(|cards| cards == combo)
+            .ok_or_else(|| format!("Not in the range: {}", cards_to_string(&[combo.0, combo.1])))?;
+        set(index, freqs);
+    }
+
+    Ok(strategy)
+}
+
+/// How much more the opponent of `player` wins by best-responding once `user_strategy` replaces
+/// the solved strategy of the current node, from the engine's best responses on a locked copy.
+fn best_response_loss(
+    game: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    user_strategy: &[f32],
+) -> Result<f64, String> {
+    let opponent = game.current_player() ^ 1;
+    let baseline = pool.install(|| compute_mes_ev(game))[opponent];
+    let node = game.history().to_vec();
+    let mut replace =
+        |game: &PostFlopGame| (game.history() == node).then(|| user_strategy.to_vec());
+    let mes_ev = with_locked_copy(
+        game,
+        memory_budget,
+        reserved_memory,
+        pool,
+        &mut replace,
+        |copy, _| compute_mes_ev(copy),
+    )?;
+    Ok((mes_ev[opponent] - baseline) as f64)
+}
+
+fn evaluate(
+    game: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    overrides: &BTreeMap<String, Vec<f64>>,
+) -> Result<StrategyEvaluation, String> {
+    if game.is_terminal_node() || game.is_chance_node() {
+        return Err("Node is not a decision node".to_string());
+    }
+
+    let player = game.current_player();
+    let actions = game.available_actions();
+    let num_actions = actions.len();
+    let num_hands = game.private_cards(player).len();
+    let strategy = game.strategy();
+    let user_strategy = build_strategy(game, overrides)?;
+
+    game.cache_normalized_weights();
+    let action_ev = game.expected_values_detail(player);
+    let weights = game.normalized_weights(player);
+
+    let mut hands = Vec::new();
+    let (mut total_weight, mut total_ev, mut total_user_ev) = (0.0, 0.0, 0.0);
+    for index in (0..num_hands).filter(|&i| weights[i] > 0.0) {
+        let dot = |s: &[f32]| {
+            (0..num_actions)
+                .map(|i| (s[i * num_hands + index] * action_ev[i * num_hands + index]) as f64)
+                .sum::<f64>()
+        };
+        let (ev, user_ev) = (dot(&strategy), dot(&user_strategy));
+        let weight = weights[index] as f64;
+        total_weight += weight;
+        total_ev += weight * ev;
+        total_user_ev += weight * user_ev;
+
+        let (c1, c2) = game.private_cards(player)[index];
+        hands.push(HandEvaluation {
+            hand: cards_to_string(&[c1, c2]),
+            weight: round(weight),
+            strategy: (0..num_actions)
+                .map(|i| round(user_strategy[i * num_hands + index] as f64))
+                .collect(),
+            ev: round(ev),
+            user_ev: round(user_ev),
+            ev_loss: round(ev - user_ev),
+        });
+    }
+    if total_weight > 0.0 {
+        total_ev /= total_weight;
+        total_user_ev /= total_weight;
+    }
+
+    let best_response_ev_loss =
+        best_response_loss(game, memory_budget, reserved_memory, pool, &user_strategy)?;
+
+    Ok(StrategyEvaluation {
+        player,
+        actions: actions.into_iter().map(encode_action).collect(),
+        ev: round(total_ev),
+        user_ev: round(total_user_ev),
+        ev_loss: round(total_ev - total_user_ev),
+        best_response_ev_loss: round(best_response_ev_loss),
+        hands,
+    })
+}
+
+/// Evaluates a strategy for the node reached by `history`. `overrides` maps combos (`AhKh`) or
+/// 13x13 cells (`AKs`) to action frequencies; other hands keep the equilibrium strategy. The
+/// stored solution is left unchanged; the best response is computed on a copy of the game.
+pub fn game_evaluate_strategy(
+    game_state: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    history: &[usize],
+    overrides: &BTreeMap<String, Vec<f64>>,
+) -> Result<StrategyEvaluation, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let saved = game_state.history().to_vec();
+    try_apply_history(game_state, history)?;
+    let result = evaluate(game_state, memory_budget, reserved_memory, pool, overrides);
+    game_state.apply_history(&saved);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::solved_river_game;
+    use rayon::ThreadPoolBuilder;
+
+    fn evaluate_root(
+        game: &mut PostFlopGame,
+        overrides: &[(&str, [f64; 3])],
+    ) -> StrategyEvaluation {
+        let overrides = overrides
+            .iter()
+            .map(|(key, freqs)| (key.to_string(), freqs.to_vec()))
+            .collect();
+        let pool = ThreadPoolBuilder::new().build().unwrap();
+        let memory_budget = MemoryBudget::new(None, None);
+        let mut reserved_memory = 0;
+        let result = game_evaluate_strategy(
+            game,
+            &memory_budget,
+            &mut reserved_memory,
+            &pool,
+            &[],
+            &overrides,
+        );
+        assert_eq!(reserved_memory, 0);
+        result.unwrap()
+    }
+
+    #[test]
+    fn solved_strategy_does_not_lose() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        let evaluation = evaluate_root(&mut game, &[]);
+        assert_eq!(evaluation.ev_loss, 0.0);
+        assert!(
+            evaluation.best_response_ev_loss.abs() < 1e-3,
+            "{}",
+            evaluation.best_response_ev_loss
+        );
+    }
+
+    #[test]
+    fn bad_strategy_loses_to_best_response() {
+        // OOP checks its top pairs and bets pot with everything else
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        let overrides = [
+            ("AK", [1.0, 0.0, 0.0]),
+            ("QQ", [0.0, 0.0, 1.0]),
+            ("76s", [0.0, 0.0, 1.0]),
+        ];
+        let evaluation = evaluate_root(&mut game, &overrides);
+        assert!(evaluation.ev_loss > 0.0, "{}", evaluation.ev_loss);
+        assert!(
+            evaluation.best_response_ev_loss > 0.0,
+            "{}",
+            evaluation.best_response_ev_loss
+        );
+        assert!(game.history().is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use crate::cards::cards_to_string;
+use crate::solver::{round, round_iter};
+use crate::state::SessionState;
+use crate::tree::{Street, encode_action, encode_line};
+
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// Results of a single decision node, seen from the acting player.
+#[derive(Serialize)]
+pub struct ExportNode {
+    /// Actions and dealt cards from the root, e.g. `X-B30-C|Td|B60`.
+    pub line: String,
+    pub board: String,
+    pub street: &'static str,
+    pub pot: i32,
+    pub player: &'static str,
+    pub actions: Vec<String>,
+    pub hands: Vec<String>,
+    pub weights: Vec<f64>,
+    pub equity: Vec<f64>,
+    pub ev: Vec<f64>,
+    pub strategy: Vec<f64>,
+    pub action_ev: Vec<f64>,
+}
+
+pub const CHUNK_SIZE: usize = 1 << 16;
+
+const CSV_HEADER: &str =
+    "line,board,street,pot,player,hand,weight,equity,ev,action,frequency,action_ev\n";
+
+/// Depth-first walk over the decision nodes reachable from the root, optionally restricted to
+/// `street` and to nodes with at most `max_depth` actions in their line. The returned lines include the dealt cards as `Action::Chance`. Only
+/// the pending histories are kept, so the game can be released between two nodes.
+pub struct DecisionNodeWalker {
+    stack: Vec<(Vec<usize>, Vec<Action>)>,
+    street: Option<Street>,
+    max_depth: Option<usize>,
+}
+
+impl DecisionNodeWalker {
+    pub fn new(street: Option<Street>, max_depth: Option<usize>) -> Self {
+        Self {
+            stack: vec![(Vec::new(), Vec::new())],
+            street,
+            max_depth,
+        }
+    }
+
+    /// Moves `game` to the next decision node and returns its line, or `None` when done.
+    pub fn next(&mut self, game: &mut PostFlopGame) -> Option<Vec<Action>> {
+        while let Some((history, line)) = self.stack.pop() {
+            game.apply_history(&history);
+            if game.is_terminal_node() {
+                continue;
+            }
+
+            let current_street = Street::from_board_len(game.current_board().len());
+            let child = |index: usize| {
+                let mut child = history.clone();
+                child.push(index);
+                child
+            };
+
+            if game.is_chance_node() {
+                if self.street.is_some_and(|s| s <= current_street) {
+                    continue;
+                }
+                let possible_cards = game.possible_cards();
+                for card in (0..52).rev() {
+                    if possible_cards & (1 << card) != 0 {
+                        let mut child_line = line.clone();
+                        child_line.push(Action::Chance(card as Card));
+                        self.stack.push((child(card), child_line));
+                    }
+                }
+                continue;
+            }
+
+            let depth = line
+                .iter()
+                .filter(|a| !matches!(a, Action::Chance(_)))
+                .count();
+            if self.max_depth.is_none_or(|d| depth < d) {
+                let actions = game.available_actions();
+                for (index, &action) in actions.iter().enumerate().rev() {
+                    let mut child_line = line.clone();
+                    child_line.push(action);
+                    self.stack.push((child(index), child_line));
+                }
+            }
+
+            if self.street.is_none_or(|s| s == current_street) {
+                return Some(line);
+            }
+        }
+        None
+    }
+}
+
+/// Decision nodes of the session's game, visited with the game locked for one node at a time.
+/// The session's current node is restored before the lock is released.
+pub struct LockedDecisionNodes<'a> {
+    state: &'a SessionState,
+    generation: u64,
+    walker: DecisionNodeWalker,
+}
+
+impl<'a> LockedDecisionNodes<'a> {
+    pub fn new(
+        state: &'a SessionState,
+        street: Option<Street>,
+        max_depth: Option<usize>,
+    ) -> Result<Self, String> {
+        let game = state.post_flop_game.lock();
+        if !game.is_solved() {
+            return Err("Game is not solved".to_string());
+        }
+        Ok(Self {
+            state,
+            generation: *state.game_generation.lock(),
+            walker: DecisionNodeWalker::new(street, max_depth),
+        })
+    }
+
+    /// Calls `f` on the next decision node, or returns `None` when done.
+    pub fn next<T>(
+        &mut self,
+        f: impl FnOnce(&mut PostFlopGame, &[Action]) -> T,
+    ) -> Result<Option<T>, String> {
+        let mut game = self.state.post_flop_game.lock();
+        if *self.state.game_generation.lock() != self.generation {
+            return Err("Game was replaced during the export".to_string());
+        }
+
+        let history = game.history().to_vec();
+        let result = self.walker.next(&mut game).map(|line| f(&mut game, &line));
+        game.apply_history(&history);
+        Ok(result)
+    }
+}
+
+pub fn export_node(game: &mut PostFlopGame, line: &[Action]) -> ExportNode {
+    let player = game.current_player();
+    let board = game.current_board();
+    let total_bet_amount = game.total_bet_amount();
+
+    let trunc = |&w: &f32| if w < 0.0005 { 0.0 } else { round(w as f64) };
+    let is_empty = |player: usize| game.weights(player).iter().all(|&w| trunc(&w) == 0.0);
+    let is_empty = is_empty(0) || is_empty(1);
+
+    let mut equity = Vec::new();
+    let mut ev = Vec::new();
+    let mut action_ev = Vec::new();
+
+    if !is_empty {
+        game.cache_normalized_weights();
+        equity.extend(round_iter(game.equity(player).iter()));
+        ev.extend(round_iter(game.expected_values(player).iter()));
+        action_ev.extend(round_iter(game.expected_values_detail(player).iter()));
+    }
+
+    ExportNode {
+        line: encode_line(line),
+        board: cards_to_string(&board),
+        street: Street::from_board_len(board.len()).as_str(),
+        pot: game.tree_config().starting_pot + total_bet_amount[0] + total_bet_amount[1],
+        player: if player == 0 { "oop" } else { "ip" },
+        actions: game
+            .available_actions()
+            .into_iter()
+            .map(encode_action)
+            .collect(),
+        hands: holes_to_strings(game.private_cards(player)).unwrap(),
+        weights: game.weights(player).iter().map(trunc).collect(),
+        equity,
+        ev,
+        strategy: round_iter(game.strategy().iter()).collect(),
+        action_ev,
+    }
+}
+
+fn write_csv_rows(out: &mut String, node: &ExportNode) {
+    let num_hands = node.hands.len();
+    let opt = |values: &[f64], index: usize| values.get(index).map(f64::to_string);
+    for (hand_index, hand) in node.hands.iter().enumerate() {
+        for (action_index, action) in node.actions.iter().enumerate() {
+            let index = action_index * num_hands + hand_index;
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                node.line,
+                node.board,
+                node.street,
+                node.pot,
+                node.player,
+                hand,
+                node.weights[hand_index],
+                opt(&node.equity, hand_index).unwrap_or_default(),
+                opt(&node.ev, hand_index).unwrap_or_default(),
+                action,
+                node.strategy[index],
+                opt(&node.action_ev, index).unwrap_or_default(),
+            )
+            .unwrap();
+        }
+    }
+}
+
+/// Writes the export of every decision node, passing chunks of about `CHUNK_SIZE` bytes to
+/// `send`. Stops early if `send` returns `false`.
+pub fn game_export(
+    state: &SessionState,
+    format: ExportFormat,
+    street: Option<Street>,
+    max_depth: Option<usize>,
+    send: &mut dyn FnMut(Vec<u8>) -> bool,
+) -> Result<(), String> {
+    let mut nodes = LockedDecisionNodes::new(state, street, max_depth)?;
+
+    let mut out = String::new();
+    if let ExportFormat::Csv = format {
+        out.push_str(CSV_HEADER);
+    }
+
+    while let Some(node) = nodes.next(export_node)? {
+        match format {
+            ExportFormat::Ndjson => {
+                out.push_str(&serde_json::to_string(&node).unwrap());
+                out.push('\n');
+            }
+            ExportFormat::Csv => write_csv_rows(&mut out, &node),
+        }
+        if out.len() >= CHUNK_SIZE && !send(std::mem::take(&mut out).into_bytes()) {
+            return Ok(());
+        }
+    }
+
+    if !out.is_empty() {
+        send(out.into_bytes());
+    }
+    Ok(())
+}
@@ -0,0 +1,587 @@
+use crate::cards::{cards_to_mask, cards_to_string, parse_cards};
+use crate::solver::{history_to_line, round};
+use crate::translation::{TranslationMethod, translate_bet};
+use crate::tree::{Street, encode_action};
+
+use postflop_solver::*;
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq)]
+enum HandActionKind {
+    Fold,
+    Check,
+    Call,
+    /// Bets and raises, with the total amount put in on the street.
+    Bet(f64),
+}
+
+struct HandAction {
+    player: String,
+    kind: HandActionKind,
+    is_allin: bool,
+    text: String,
+}
+
+/// The part of a hand history needed for replay. `streets` and `pots` are indexed by preflop,
+/// flop, turn and river; `pots` is the pot at the start of each street.
+struct HandHistory {
+    hero: Option<(String, (Card, Card))>,
+    board: Vec<Card>,
+    streets: [Vec<HandAction>; 4],
+    pots: [f64; 4],
+}
+
+fn parse_amount(str: &str) -> Result<f64, String> {
+    let trimmed = str.trim_start_matches(['$', '€', '£']).replace(',', "");
+    trimmed
+        .parse()
+        .map_err(|_| format!("Invalid amount: {str}"))
+}
+
+fn parse_bracketed_cards(line: &str) -> Result<Vec<Card>, String> {
+    let mut cards = Vec::new();
+    for part in line.split('[').skip(1) {
+        let Some((inner, _)) = part.split_once(']') else {
+            return Err(format!("Invalid cards: {line}"));
+        };
+        cards.extend(parse_cards(inner)?);
+    }
+    Ok(cards)
+}
+
+/// Parses a PokerStars-style hand history.
+fn parse_hand_history(text: &str) -> Result<HandHistory, String> {
+    let mut hero = None;
+    let mut board = Vec::new();
+    let mut streets: [Vec<HandAction>; 4] = Default::default();
+    let mut pots = [0.0; 4];
+    let mut street = None;
+
+    // contributions of each player on the current street
+    let mut street_bets: Vec<(String, f64)> = Vec::new();
+    let mut pot = 0.0;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(marker) = line.strip_prefix("*** ") {
+            let next = if marker.starts_with("HOLE CARDS") {
+                Some(0)
+            } else if marker.starts_with("FLOP") {
+                Some(1)
+            } else if marker.starts_with("TURN") {
+                Some(2)
+            } else if marker.starts_with("RIVER") {
+                Some(3)
+            } else {
+                None
+            };
+
+            pot += street_bets.iter().map(|(_, amount)| amount).sum::<f64>();
+            street_bets.clear();
+            street = next;
+
+            match next {
+                Some(0) => {}
+                Some(index) => {
+                    pots[index] = pot;
+                    board = parse_bracketed_cards(line)?;
+                }
+                None => break,
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("Dealt to ") {
+            if let Some((name, _)) = rest.rsplit_once(" [") {
+                let cards = parse_bracketed_cards(rest)?;
+                if cards.len() != 2 {
+                    return Err(format!("Invalid hole cards: {line}"));
+                }
+                hero = Some((name.to_string(), (cards[0], cards[1])));
+            }
+            continue;
+        }
+
+        if let Some(rest) = line.strip_prefix("Uncalled bet (") {
+            if let Some((amount, name)) = rest.split_once(") returned to ") {
+                let amount = parse_amount(amount)?;
+                if let Some(entry) = street_bets.iter_mut().find(|(n, _)| n == name) {
+                    entry.1 -= amount;
+                }
+            }
+            continue;
+        }
+
+        let Some((player, action)) = line.rsplit_once(": ") else {
+            continue;
+        };
+        let body = action.trim_end_matches(" and is all-in");
+        let is_allin = body.len() != action.len();
+        let words = body.split_whitespace().collect::<Vec<_>>();
+        let contribution = street_bets
+            .iter()
+            .position(|(n, _)| n == player)
+            .unwrap_or_else(|| {
+                street_bets.push((player.to_string(), 0.0));
+                street_bets.len() - 1
+            });
+
+        let kind = match words.as_slice() {
+            ["posts", .., amount] => {
+                let amount = parse_amount(amount)?;
+                // the ante is dead money and does not count toward the street bet
+                if action.contains("ante") {
+                    pot += amount;
+                } else {
+                    street_bets[contribution].1 += amount;
+                }
+                continue;
+            }
+            ["folds", ..] => HandActionKind::Fold,
+            ["checks", ..] => HandActionKind::Check,
+            ["calls", amount, ..] => {
+                street_bets[contribution].1 += parse_amount(amount)?;
+                HandActionKind::Call
+            }
+            ["bets", amount, ..] => {
+                let amount = parse_amount(amount)?;
+                street_bets[contribution].1 += amount;
+                HandActionKind::Bet(street_bets[contribution].1)
+            }
+            ["raises", _, "to", amount, ..] => {
+                let amount = parse_amount(amount)?;
+                street_bets[contribution].1 = amount;
+                HandActionKind::Bet(amount)
+            }
+            _ => continue,
+        };
+
+        if let Some(index) = street {
+            streets[index].push(HandAction {
+                player: player.to_string(),
+                kind,
+                is_allin,
+                text: action.to_string(),
+            });
+        }
+    }
+
+    // a street with actions needs its card; the board of the last street header covers all
+    for (index, actions) in streets.iter().enumerate().skip(1) {
+        if !actions.is_empty() && board.len() < index + 2 {
+            return Err(format!(
+                "Missing {} card",
+                Street::from_board_len(index + 2).as_str()
+            ));
+        }
+    }
+
+    Ok(HandHistory {
+        hero,
+        board,
+        streets,
+        pots,
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeroDecision {
+    line: String,
+    board: String,
+    actions: Vec<String>,
+    strategy: Vec<f64>,
+    action_ev: Vec<f64>,
+    action_taken: String,
+    mapped_action: String,
+    ev_loss: Option<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandReplay {
+    hero: String,
+    hero_hand: String,
+    hero_player: &'static str,
+    /// Chips of the solved game per unit of the hand history.
+    scale: f64,
+    decisions: Vec<HeroDecision>,
+    warnings: Vec<String>,
+}
+
+/// Picks the available action corresponding to `action`. Off-tree bets and raises are translated
+/// to the most likely tree size with the pseudo-harmonic mapping.
+fn map_action(game: &mut PostFlopGame, action: &HandAction, scale: f64) -> Option<usize> {
+    let available = game.available_actions();
+    let find = |target: Action| available.iter().position(|&a| a == target);
+    match action.kind {
+        HandActionKind::Fold => find(Action::Fold),
+        HandActionKind::Check => find(Action::Check),
+        HandActionKind::Call => find(Action::Call),
+        HandActionKind::Bet(amount) => {
+            let allin = available.iter().position(|a| matches!(a, Action::AllIn(_)));
+            if action.is_allin && allin.is_some() {
+                return allin;
+            }
+            let amount = (amount * scale).round() as i32;
+            // the line must keep a bet here, even when the size is closer to checking
+            translate_bet(game, amount, TranslationMethod::PseudoHarmonic)
+                .ok()?
+                .into_iter()
+                .filter(|translated| {
+                    matches!(
+                        available[translated.index],
+                        Action::Bet(_) | Action::Raise(_) | Action::AllIn(_)
+                    )
+                })
+                .max_by(|a, b| a.probability.total_cmp(&b.probability))
+                .map(|translated| translated.index)
+        }
+    }
+}
+
+fn replay(
+    game: &mut PostFlopGame,
+    hand: &HandHistory,
+    hero_name: Option<String>,
+) -> Result<HandReplay, String> {
+    let mut warnings = Vec::new();
+
+    let (hero, hero_cards) = match (hero_name, &hand.hero) {
+        (Some(name), Some((dealt, cards))) if &name == dealt => (name, *cards),
+        (None, Some((dealt, cards))) => (dealt.clone(), *cards),
+        (Some(name), _) => return Err(format!("Hole cards of {name} are not known")),
+        (None, None) => return Err("Hero is not found".to_string()),
+    };
+    let hero_cards = (
+        hero_cards.0.min(hero_cards.1),
+        hero_cards.0.max(hero_cards.1),
+    );
+
+    let root_board = game.current_board();
+    let initial = root_board.len() - 2;
+    if hand.board.len() < root_board.len() {
+        return Err("Hand history does not reach the street of the solved game".to_string());
+    }
+    let mut flop = hand.board[..3].to_vec();
+    let mut root_flop = root_board[..3].to_vec();
+    flop.sort_unstable();
+    root_flop.sort_unstable();
+    if flop != root_flop || hand.board[3..root_board.len()] != root_board[3..] {
+        return Err(format!(
+            "Board mismatch: solved {}, hand {}",
+            cards_to_string(&root_board),
+            cards_to_string(&hand.board)
+        ));
+    }
+
+    let actions = hand.streets[initial..].iter().flatten().collect::<Vec<_>>();
+    let mut players = Vec::<&str>::new();
+    for action in &actions {
+        if !players.contains(&action.player.as_str()) {
+            players.push(&action.player);
+        }
+    }
+    if players.len() != 2 {
+        return Err("Only heads-up pots are supported".to_string());
+    }
+    let Some(hero_player) = players.iter().position(|&p| p == hero) else {
+        return Err(format!("{hero} does not act in the solved streets"));
+    };
+
+    let scale = game.tree_config().starting_pot as f64 / hand.pots[initial];
+    if !scale.is_finite() {
+        return Err("Invalid pot size".to_string());
+    }
+
+    let hero_index = game
+        .private_cards(hero_player)
+        .iter()
+        .position(|&cards| cards == hero_cards);
+    if hero_index.is_none() {
+        let hero_mask = 1 << hero_cards.0 | 1 << hero_cards.1;
+        warnings.push(match cards_to_mask(&hand.board) & hero_mask {
+            0 => "Hero's hand is not in the range of the solved game".to_string(),
+            _ => "Hero's hand conflicts with the board".to_string(),
+        });
+    }
+
+    let mut history = Vec::new();
+    let mut decisions = Vec::new();
+
+    for street in initial..4 {
+        if street > initial {
+            if hand.streets[street].is_empty() && hand.board.len() < street + 2 {
+                break;
+            }
+            if !game.is_chance_node() {
+                return Err(format!(
+                    "Unexpected {} card",
+                    Street::from_board_len(street + 2).as_str()
+                ));
+            }
+            let Some(&card) = hand.board.get(street + 1) else {
+                return Err(format!(
+                    "Missing {} card",
+                    Street::from_board_len(street + 2).as_str()
+                ));
+            };
+            if game.possible_cards() & (1 << card) == 0 {
+                return Err(format!(
+                    "Card cannot be dealt: {}",
+                    cards_to_string(&[card])
+                ));
+            }
+            game.play(card as usize);
+            history.push(card as isize);
+        }
+
+        for action in &hand.streets[street] {
+            if game.is_terminal_node() || game.is_chance_node() {
+                return Err(format!(
+                    "Line ended before: {}: {}",
+                    action.player, action.text
+                ));
+            }
+
+            let available = game.available_actions();
+            let Some(index) = map_action(game, action, scale) else {
+                return Err(format!(
+                    "No matching action for: {}: {}",
+                    action.player, action.text
+                ));
+            };
+
+            let player = game.current_player();
+            if players[player] != action.player {
+                return Err(format!(
+                    "Unexpected actor: {}: {}",
+                    action.player, action.text
+                ));
+            }
+
+            if player == hero_player
+                && let Some(hand_index) = hero_index
+            {
+                let line = history_to_line(game, &history)?;
+                let num_hands = game.private_cards(player).len();
+                let strategy = game.strategy();
+                let hand_strategy = (0..available.len())
+                    .map(|i| round(strategy[i * num_hands + hand_index] as f64))
+                    .collect();
+                let mut action_ev = Vec::new();
+                let mut ev_loss = None;
+                if game.weights(player)[hand_index] > 0.0 {
+                    game.cache_normalized_weights();
+                    let ev = game.expected_values_detail(player);
+                    action_ev = (0..available.len())
+                        .map(|i| round(ev[i * num_hands + hand_index] as f64))
+                        .collect::<Vec<_>>();
+                    let best = action_ev.iter().cloned().fold(f64::MIN, f64::max);
+                    ev_loss = Some(round(best - action_ev[index]));
+                } else {
+                    warnings.push(format!("Hero's hand is not in range at {line}"));
+                }
+                decisions.push(HeroDecision {
+                    line,
+                    board: cards_to_string(&game.current_board()),
+                    actions: available.iter().cloned().map(encode_action).collect(),
+                    strategy: hand_strategy,
+                    action_ev,
+                    action_taken: action.text.clone(),
+                    mapped_action: encode_action(available[index]),
+                    ev_loss,
+                });
+            }
+
+            history.push(index as isize);
+            game.play(index);
+        }
+    }
+
+    Ok(HandReplay {
+        hero,
+        hero_hand: cards_to_string(&[hero_cards.0, hero_cards.1]),
+        hero_player: if hero_player == 0 { "oop" } else { "ip" },
+        scale: round(scale),
+        decisions,
+        warnings,
+    })
+}
+
+/// Replays a PokerStars-style hand history on the solved game. The hand history must reach the
+/// street the game starts on, with the same board, and be heads-up from there. Off-tree sizes are
+/// mapped to the closest available action after scaling the pot to the game's starting pot.
+pub fn game_replay_hand_history(
+    game_state: &mut PostFlopGame,
+    text: &str,
+    hero: Option<String>,
+) -> Result<HandReplay, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let hand = parse_hand_history(text)?;
+    let history = game_state.history().to_vec();
+    game_state.back_to_root();
+    let result = replay(game_state, &hand, hero);
+    game_state.apply_history(&history);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::solved_river_game;
+
+    const HAND: &str = "\
+PokerStars Hand #1: Hold'em No Limit ($0.50/$1.00 USD) - 2024/01/01 12:00:00 ET
+Table 'Test' 6-max Seat #1 is the button
+Seat 1: Villain ($100 in chips)
+Seat 2: SB ($100 in chips)
+Seat 3: Hero ($100 in chips)
+SB: posts small blind $0.50
+Hero: posts big blind $1
+*** HOLE CARDS ***
+Dealt to Hero [Ah Kd]
+Villain: raises $1.50 to $2.50
+SB: folds
+Hero: calls $1.50
+*** FLOP *** [Ts 7h 2c]
+Hero: checks
+Villain: bets $1.75
+Hero: raises $4.25 to $6
+Villain: calls $4.25
+*** TURN *** [Ts 7h 2c] [Kc]
+Hero: bets $9
+Villain: raises $81.50 to $91.50 and is all-in
+Hero: folds
+Uncalled bet ($82.50) returned to Villain
+*** SUMMARY ***
+Total pot $35.50
+";
+
+    #[test]
+    fn parse_pokerstars_hand() {
+        let hand = parse_hand_history(HAND).unwrap();
+        let (hero, cards) = hand.hero.unwrap();
+        assert_eq!(hero, "Hero");
+        assert_eq!(cards_to_string(&[cards.0, cards.1]), "AhKd");
+        assert_eq!(cards_to_string(&hand.board), "Ts7h2cKc");
+
+        // blinds and the folded small blind are in the flop pot
+        assert_eq!(hand.pots[1], 5.5);
+        assert_eq!(hand.pots[2], 17.5);
+
+        let flop = &hand.streets[1];
+        assert_eq!(flop.len(), 4);
+        assert!(flop[0].kind == HandActionKind::Check);
+        assert!(flop[1].kind == HandActionKind::Bet(1.75));
+        assert!(flop[2].kind == HandActionKind::Bet(6.0));
+        assert!(flop[3].kind == HandActionKind::Call);
+
+        let turn = &hand.streets[2];
+        assert!(turn[1].kind == HandActionKind::Bet(91.5) && turn[1].is_allin);
+        assert!(turn[2].kind == HandActionKind::Fold);
+        assert!(hand.streets[3].is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_missing_street_card() {
+        let text = HAND.replace("[Ts 7h 2c] [Kc]", "[Ts 7h 2c]");
+        assert!(parse_hand_history(&text).is_err());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_input() {
+        assert!(parse_hand_history(&HAND.replace("[Ah Kd]", "[Ah]")).is_err());
+        assert!(parse_hand_history(&HAND.replace("[Ts 7h 2c]", "[Ts 7h 2x]")).is_err());
+        assert!(parse_hand_history(&HAND.replace("[Ts 7h 2c]", "[Ts 7h 7h]")).is_err());
+        assert!(parse_hand_history(&HAND.replace("bets $9", "bets $9x")).is_err());
+        assert!(parse_hand_history(&HAND.replace("[Kc]", "[Kc")).is_err());
+    }
+
+    const RIVER_HAND: &str = "\
+PokerStars Hand #2: Hold'em No Limit ($0.50/$1.00 USD) - 2024/01/01 12:05:00 ET
+Table 'Test' 6-max Seat #1 is the button
+Seat 1: Villain ($100 in chips)
+Seat 2: SB ($100 in chips)
+Seat 3: Hero ($100 in chips)
+SB: posts small blind $0.50
+Hero: posts big blind $1
+*** HOLE CARDS ***
+Dealt to Hero [Ah Kd]
+Villain: raises $1.25 to $2.25
+SB: folds
+Hero: calls $1.25
+*** FLOP *** [Ts 7h 2c]
+Hero: checks
+Villain: checks
+*** TURN *** [Ts 7h 2c] [Kc]
+Hero: checks
+Villain: checks
+*** RIVER *** [Ts 7h 2c Kc] [5d]
+Hero: bets $4
+Villain: calls $4
+*** SUMMARY ***
+Total pot $13
+";
+
+    #[test]
+    fn replay_translates_off_tree_sizes() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        let replay = game_replay_hand_history(&mut game, RIVER_HAND, None).unwrap();
+        assert_eq!(replay.hero_player, "oop");
+        assert!(replay.warnings.is_empty());
+
+        // the pot of $5 is scaled to 100 chips, so the $4 bet is 80 chips and maps to the pot bet
+        assert_eq!(replay.scale, 20.0);
+        assert_eq!(replay.decisions.len(), 1);
+        let decision = &replay.decisions[0];
+        assert_eq!(decision.action_taken, "bets $4");
+        assert_eq!(decision.mapped_action, encode_action(Action::Bet(100)));
+        let taken = decision
+            .actions
+            .iter()
+            .position(|action| *action == decision.mapped_action)
+            .unwrap();
+
+        // the EVs are the hand's action EVs at the root, and the loss is measured from the best
+        let hand_index = game
+            .private_cards(0)
+            .iter()
+            .position(|&cards| {
+                cards == (card_from_str("Kd").unwrap(), card_from_str("Ah").unwrap())
+            })
+            .unwrap();
+        game.back_to_root();
+        game.cache_normalized_weights();
+        let ev = game.expected_values_detail(0);
+        let num_hands = game.private_cards(0).len();
+        for (i, &action_ev) in decision.action_ev.iter().enumerate() {
+            assert_eq!(action_ev, round(ev[i * num_hands + hand_index] as f64));
+        }
+        let best = decision.action_ev.iter().cloned().fold(f64::MIN, f64::max);
+        assert_eq!(
+            decision.ev_loss,
+            Some(round(best - decision.action_ev[taken]))
+        );
+        assert!(decision.ev_loss.unwrap() >= 0.0);
+    }
+
+    #[test]
+    fn replay_tells_conflicts_from_hands_out_of_range() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+
+        let text = RIVER_HAND.replace("[Ah Kd]", "[Ah Qd]");
+        let replay = game_replay_hand_history(&mut game, &text, None).unwrap();
+        assert!(replay.decisions.is_empty());
+        assert_eq!(
+            replay.warnings,
+            ["Hero's hand is not in the range of the solved game"]
+        );
+
+        let text = RIVER_HAND.replace("[Ah Kd]", "[Ah Kc]");
+        let replay = game_replay_hand_history(&mut game, &text, None).unwrap();
+        assert_eq!(replay.warnings, ["Hero's hand conflicts with the board"]);
+    }
+}
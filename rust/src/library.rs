@@ -0,0 +1,605 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LIBRARY_FILE: &str = "library.json";
+const EXPORT_VERSION: u64 = 2;
+
+/// Same shape as `DbItem | DbGroup` in `db.ts`.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryEntry {
+    #[serde(default)]
+    pub id: u64,
+    pub name0: String,
+    pub name1: String,
+    pub name2: String,
+    pub name3: String,
+    pub is_group: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+impl LibraryEntry {
+    fn path(&self) -> Vec<&str> {
+        [&self.name0, &self.name1, &self.name2, &self.name3]
+            .into_iter()
+            .map(String::as_str)
+            .take_while(|name| !name.is_empty())
+            .collect()
+    }
+
+    fn from_path(path: &[String], is_group: bool, value: Option<Value>) -> Self {
+        let name = |i: usize| path.get(i).cloned().unwrap_or_default();
+        Self {
+            id: 0,
+            name0: name(0),
+            name1: name(1),
+            name2: name(2),
+            name3: name(3),
+            is_group: is_group as u8,
+            value,
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryData {
+    next_id: u64,
+    ranges: Vec<LibraryEntry>,
+    configurations: Vec<LibraryEntry>,
+}
+
+pub struct Library {
+    path: PathBuf,
+    data: LibraryData,
+}
+
+impl Library {
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(LIBRARY_FILE);
+        let data = match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(Self { path, data })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        let text = serde_json::to_string_pretty(&self.data).map_err(|e| e.to_string())?;
+        fs::write(&tmp_path, text).map_err(|e| e.to_string())?;
+        fs::rename(&tmp_path, &self.path).map_err(|e| e.to_string())
+    }
+
+    fn table(&self, store: &str) -> Option<&Vec<LibraryEntry>> {
+        match store {
+            "ranges" => Some(&self.data.ranges),
+            "configurations" => Some(&self.data.configurations),
+            _ => None,
+        }
+    }
+
+    fn table_mut(&mut self, store: &str) -> Option<&mut Vec<LibraryEntry>> {
+        match store {
+            "ranges" => Some(&mut self.data.ranges),
+            "configurations" => Some(&mut self.data.configurations),
+            _ => None,
+        }
+    }
+
+    /// Applies `f` to a copy of the table and persists it if `f` returns `true`. If saving fails,
+    /// the table is left unchanged and the error is returned.
+    fn transaction(
+        &mut self,
+        store: &str,
+        f: impl FnOnce(&mut Vec<LibraryEntry>, &mut u64) -> bool,
+    ) -> Result<bool, String> {
+        let Some(table) = self.table(store) else {
+            return Ok(false);
+        };
+
+        let mut table = table.clone();
+        let mut next_id = self.data.next_id;
+        if !f(&mut table, &mut next_id) {
+            return Ok(false);
+        }
+
+        let old_table = std::mem::replace(self.table_mut(store).unwrap(), table);
+        let old_next_id = std::mem::replace(&mut self.data.next_id, next_id);
+        if let Err(e) = self.save() {
+            *self.table_mut(store).unwrap() = old_table;
+            self.data.next_id = old_next_id;
+            return Err(format!("Failed to save library ({e})"));
+        }
+
+        Ok(true)
+    }
+}
+
+fn insert(table: &mut Vec<LibraryEntry>, next_id: &mut u64, mut entry: LibraryEntry) -> bool {
+    let path = entry.path();
+    let depth = path.len();
+    if depth == 0 || table.iter().any(|e| e.path() == path) {
+        return false;
+    }
+
+    // parent check
+    if depth > 1
+        && !table
+            .iter()
+            .any(|e| e.is_group == 1 && e.path() == path[..depth - 1])
+    {
+        return false;
+    }
+
+    *next_id += 1;
+    entry.id = *next_id;
+    table.push(entry);
+    true
+}
+
+pub fn library_get_array(library: &Library, store: &str) -> Vec<LibraryEntry> {
+    library.table(store).cloned().unwrap_or_default()
+}
+
+pub fn library_add_item(
+    library: &mut Library,
+    store: &str,
+    mut item: LibraryEntry,
+) -> Result<bool, String> {
+    item.is_group = 0;
+    library.transaction(store, |table, next_id| insert(table, next_id, item))
+}
+
+pub fn library_add_group(
+    library: &mut Library,
+    store: &str,
+    mut group: LibraryEntry,
+) -> Result<bool, String> {
+    if !group.name3.is_empty() {
+        return Ok(false);
+    }
+    group.is_group = 1;
+    group.value = None;
+    library.transaction(store, |table, next_id| insert(table, next_id, group))
+}
+
+pub fn library_overwrite_item(
+    library: &mut Library,
+    store: &str,
+    item: LibraryEntry,
+) -> Result<bool, String> {
+    library.transaction(store, |table, _| {
+        let path = item.path();
+        match table
+            .iter_mut()
+            .find(|e| e.is_group == 0 && e.path() == path)
+        {
+            Some(entry) => {
+                entry.value = item.value;
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+pub fn library_rename_item(
+    library: &mut Library,
+    store: &str,
+    item: LibraryEntry,
+    new_name: String,
+) -> Result<bool, String> {
+    let path = item.path();
+    let depth = path.len();
+    if depth == 0 || new_name.is_empty() {
+        return Ok(false);
+    }
+
+    let mut new_path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    new_path[depth - 1] = new_name;
+
+    library.transaction(store, |table, _| {
+        if table.iter().any(|e| e.path() == new_path) {
+            return false;
+        }
+
+        let mut count = 0;
+        for entry in table.iter_mut() {
+            let entry_path = entry.path();
+            if entry_path.len() >= depth && entry_path[..depth] == path[..] {
+                let mut renamed = entry_path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+                renamed[depth - 1] = new_path[depth - 1].clone();
+                let value = entry.value.take();
+                *entry = LibraryEntry {
+                    id: entry.id,
+                    ..LibraryEntry::from_path(&renamed, entry.is_group == 1, value)
+                };
+                count += 1;
+            }
+        }
+
+        count > 0
+    })
+}
+
+pub fn library_delete_item(
+    library: &mut Library,
+    store: &str,
+    item: LibraryEntry,
+) -> Result<bool, String> {
+    let path = item.path();
+    let depth = path.len();
+    let is_group = item.is_group == 1;
+
+    library.transaction(store, |table, _| {
+        if !table
+            .iter()
+            .any(|e| (e.is_group == 1) == is_group && e.path() == path)
+        {
+            return false;
+        }
+
+        table.retain(|e| {
+            let entry_path = e.path();
+            match is_group {
+                true => entry_path.len() < depth || entry_path[..depth] != path[..],
+                false => entry_path != path,
+            }
+        });
+
+        true
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonItem {
+    path: Vec<String>,
+    is_group: bool,
+    value: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonExport {
+    version: u64,
+    name: String,
+    data: Vec<JsonItem>,
+}
+
+/// Database dump written by `dexie-export-import`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DexieExport {
+    format_name: String,
+    data: DexieDatabase,
+}
+
+#[derive(Deserialize)]
+struct DexieDatabase {
+    data: Vec<DexieTable>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DexieTable {
+    table_name: String,
+    rows: Vec<LibraryEntry>,
+}
+
+/// Reads the items of `store` from either supported format, parents first.
+fn import_items(store: &str, data: Value) -> Result<Vec<JsonItem>, String> {
+    if data.get("formatName").is_none() {
+        let export: JsonExport =
+            serde_json::from_value(data).map_err(|e| format!("Parse error ({e})"))?;
+        if export.version != EXPORT_VERSION {
+            return Err("Version mismatch".to_string());
+        }
+        if export.name != store {
+            return Err("Data type mismatch".to_string());
+        }
+        return Ok(export.data);
+    }
+
+    let export: DexieExport =
+        serde_json::from_value(data).map_err(|e| format!("Parse error ({e})"))?;
+    if export.format_name != "dexie" {
+        return Err("Unknown format".to_string());
+    }
+    let Some(table) = export.data.data.into_iter().find(|t| t.table_name == store) else {
+        return Err("Data type mismatch".to_string());
+    };
+
+    // rows are in id order; sort them so that every group comes before its items
+    let mut items = table
+        .rows
+        .into_iter()
+        .map(|row| JsonItem {
+            path: row.path().into_iter().map(str::to_string).collect(),
+            is_group: row.is_group == 1,
+            value: row.value,
+        })
+        .collect::<Vec<_>>();
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(items)
+}
+
+/// Imports a file produced by "Export JSON" in `DbItemPicker.vue`, or a dump of the browser
+/// database in the format of `dexie-export-import`. Items that collide with an existing item of a
+/// different value are renamed to `name (2)`, `name (3)`, ..., as the client does.
+pub fn library_import(library: &mut Library, store: &str, data: Value) -> Option<String> {
+    let items = match import_items(store, data) {
+        Ok(items) => items,
+        Err(e) => return Some(e),
+    };
+
+    let mut seen = HashMap::new();
+    seen.insert(Vec::new(), true);
+    for item in &items {
+        let path = item
+            .path
+            .iter()
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<_>>();
+        let valid_value = match store {
+            "ranges" => matches!(item.value, Some(Value::String(_))),
+            _ => matches!(item.value, Some(Value::Object(_))),
+        };
+        if path.len() > if item.is_group { 3 } else { 4 }
+            || path.iter().any(String::is_empty)
+            || (!item.is_group && !valid_value)
+            || seen.get(&path[..path.len().saturating_sub(1)]) != Some(&true)
+            || seen.contains_key(&path)
+        {
+            return Some("Invalid data".to_string());
+        }
+        seen.insert(path, item.is_group);
+    }
+
+    let success = library.transaction(store, |table, next_id| {
+        for item in items {
+            let mut path = item
+                .path
+                .iter()
+                .map(|s| s.trim().to_string())
+                .collect::<Vec<_>>();
+            let depth = path.len();
+            let name = path[depth - 1].clone();
+            let find = |table: &Vec<LibraryEntry>, path: &[String]| {
+                table.iter().find(|e| e.path() == path).cloned()
+            };
+
+            if item.is_group {
+                match find(table, &path) {
+                    Some(master) if master.is_group == 0 => return false,
+                    Some(_) => {}
+                    None => {
+                        insert(table, next_id, LibraryEntry::from_path(&path, true, None));
+                    }
+                }
+            } else {
+                let mut i = 2;
+                let mut master = find(table, &path);
+                while master
+                    .as_ref()
+                    .is_some_and(|m| m.is_group == 1 || m.value != item.value)
+                {
+                    path[depth - 1] = format!("{name} ({i})");
+                    i += 1;
+                    master = find(table, &path);
+                }
+                if master.is_none() {
+                    insert(
+                        table,
+                        next_id,
+                        LibraryEntry::from_path(&path, false, item.value),
+                    );
+                }
+            }
+        }
+        true
+    });
+
+    match success {
+        Ok(true) => None,
+        Ok(false) => Some("Failed to import data".to_string()),
+        Err(e) => Some(e),
+    }
+}
+
+/// Exports the store in the same format as "Export JSON" in `DbItemPicker.vue`.
+pub fn library_export(library: &Library, store: &str) -> Value {
+    let mut entries = library_get_array(library, store);
+    entries.sort_by(|a, b| a.path().cmp(&b.path()));
+
+    let data = entries
+        .into_iter()
+        .map(|e| {
+            let mut item = json!({ "path": e.path(), "isGroup": e.is_group == 1 });
+            if let Some(value) = e.value {
+                item["value"] = value;
+            }
+            item
+        })
+        .collect::<Vec<_>>();
+
+    json!({ "version": EXPORT_VERSION, "name": store, "data": data })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_temp(name: &str) -> Library {
+        let dir = std::env::temp_dir().join(format!("library-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Library::open(&dir).unwrap()
+    }
+
+    fn entry(path: &[&str], value: Option<&str>) -> LibraryEntry {
+        let path = path.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        LibraryEntry::from_path(&path, value.is_none(), value.map(|v| json!(v)))
+    }
+
+    fn paths(library: &Library) -> Vec<String> {
+        let mut paths = library_get_array(library, "ranges")
+            .iter()
+            .map(|e| e.path().join("/"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn crud_is_saved_to_disk() {
+        let mut library = open_temp("crud");
+        assert_eq!(
+            library_add_group(&mut library, "ranges", entry(&["a"], None)),
+            Ok(true)
+        );
+        assert_eq!(
+            library_add_item(&mut library, "ranges", entry(&["a", "x"], Some("AA"))),
+            Ok(true)
+        );
+        assert_eq!(
+            library_add_item(&mut library, "ranges", entry(&["a", "x"], Some("KK"))),
+            Ok(false)
+        );
+        assert_eq!(
+            library_add_item(&mut library, "ranges", entry(&["b", "x"], Some("KK"))),
+            Ok(false)
+        );
+        assert_eq!(
+            library_add_item(&mut library, "nothing", entry(&["x"], Some("KK"))),
+            Ok(false)
+        );
+
+        let item = entry(&["a", "x"], Some("QQ"));
+        assert_eq!(
+            library_overwrite_item(&mut library, "ranges", item),
+            Ok(true)
+        );
+        let group = entry(&["a"], None);
+        assert_eq!(
+            library_rename_item(&mut library, "ranges", group, "b".to_string()),
+            Ok(true)
+        );
+        assert_eq!(paths(&library), ["b", "b/x"]);
+
+        // the file is replaced through a temporary file, which does not stay behind
+        assert!(!library.path.with_extension("json.tmp").exists());
+        let reopened = Library::open(library.path.parent().unwrap()).unwrap();
+        assert_eq!(paths(&reopened), ["b", "b/x"]);
+        let item = &library_get_array(&reopened, "ranges")[1];
+        assert_eq!(item.value, Some(json!("QQ")));
+
+        let group = entry(&["b"], None);
+        assert_eq!(library_delete_item(&mut library, "ranges", group), Ok(true));
+        assert!(paths(&library).is_empty());
+    }
+
+    #[test]
+    fn groups_nest_three_levels_deep() {
+        let mut library = open_temp("nesting");
+        let add_group = |library: &mut Library, path: &[&str]| {
+            library_add_group(library, "ranges", entry(path, None))
+        };
+        assert_eq!(add_group(&mut library, &["a"]), Ok(true));
+        assert_eq!(add_group(&mut library, &["a", "b"]), Ok(true));
+        assert_eq!(add_group(&mut library, &["a", "b", "c"]), Ok(true));
+        assert_eq!(add_group(&mut library, &["a", "b", "c", "d"]), Ok(false));
+        assert_eq!(add_group(&mut library, &["x", "y"]), Ok(false));
+
+        let item = entry(&["a", "b", "c", "d"], Some("AA"));
+        assert_eq!(library_add_item(&mut library, "ranges", item), Ok(true));
+
+        // deleting an item leaves its groups, deleting a group takes everything below it
+        let item = entry(&["a", "b", "c", "d"], Some("AA"));
+        assert_eq!(library_delete_item(&mut library, "ranges", item), Ok(true));
+        assert_eq!(paths(&library), ["a", "a/b", "a/b/c"]);
+        let group = entry(&["a", "b"], None);
+        assert_eq!(library_delete_item(&mut library, "ranges", group), Ok(true));
+        assert_eq!(paths(&library), ["a"]);
+    }
+
+    #[test]
+    fn failed_save_is_reported_and_rolled_back() {
+        let mut library = open_temp("failed-save");
+        assert_eq!(
+            library_add_group(&mut library, "ranges", entry(&["a"], None)),
+            Ok(true)
+        );
+
+        // a directory in place of the file makes the rename fail
+        fs::remove_file(&library.path).unwrap();
+        fs::create_dir(&library.path).unwrap();
+        let item = entry(&["a", "x"], Some("AA"));
+        assert!(library_add_item(&mut library, "ranges", item).is_err());
+        assert_eq!(paths(&library), ["a"]);
+    }
+
+    #[test]
+    fn import_reads_both_formats() {
+        let mut library = open_temp("import");
+        let item = entry(&["a"], Some("AA"));
+        assert_eq!(library_add_item(&mut library, "ranges", item), Ok(true));
+
+        let export = json!({
+            "version": EXPORT_VERSION,
+            "name": "ranges",
+            "data": [
+                { "path": ["a"], "isGroup": false, "value": "KK" },
+                { "path": ["g"], "isGroup": true },
+                { "path": ["g", "b"], "isGroup": false, "value": "QQ" },
+            ],
+        });
+        assert_eq!(library_import(&mut library, "ranges", export.clone()), None);
+        assert_eq!(paths(&library), ["a", "a (2)", "g", "g/b"]);
+
+        // importing the same file again changes nothing
+        assert_eq!(library_import(&mut library, "ranges", export.clone()), None);
+        assert_eq!(paths(&library), ["a", "a (2)", "g", "g/b"]);
+        assert!(library_import(&mut library, "configurations", export).is_some());
+
+        let row = |id, path: [&str; 2], is_group, value: Option<&str>| {
+            json!({
+                "id": id, "name0": path[0], "name1": path[1], "name2": "", "name3": "",
+                "isGroup": is_group, "value": value,
+            })
+        };
+        let dexie = json!({
+            "formatName": "dexie",
+            "formatVersion": 1,
+            "data": {
+                "databaseName": "DesktopPostflopDB",
+                "databaseVersion": 2,
+                "tables": [],
+                "data": [{
+                    "tableName": "ranges",
+                    "inbound": true,
+                    "rows": [
+                        row(2, ["h", "c"], 0, Some("JJ")),
+                        row(1, ["h", ""], 1, None),
+                        row(3, ["g", ""], 1, None),
+                        row(4, ["g", "b"], 0, Some("QQ")),
+                    ],
+                }],
+            },
+        });
+        assert_eq!(library_import(&mut library, "ranges", dexie.clone()), None);
+        assert_eq!(paths(&library), ["a", "a (2)", "g", "g/b", "h", "h/c"]);
+        assert!(library_import(&mut library, "configurations", dexie).is_some());
+
+        let mut invalid = json!({ "version": EXPORT_VERSION, "name": "ranges", "data": [] });
+        invalid["data"] = json!([{ "path": ["x", "y"], "isGroup": false, "value": "AA" }]);
+        assert!(library_import(&mut library, "ranges", invalid).is_some());
+    }
+}
@@ -1,25 +1,56 @@
 mod bunching;
+mod cards;
+mod columnar;
+mod config;
+mod evaluation;
+mod export;
+mod hand_history;
+mod library;
+mod presets;
 mod range;
+mod sampling;
+mod simplification;
 mod solver;
 mod state;
+mod templates;
+mod trainer;
+mod translation;
 mod tree;
 
-use crate::state::SessionState;
+use crate::cards::deserialize_history;
+use crate::columnar::ColumnarFormat;
+use crate::config::VersionedConfig;
+use crate::export::ExportFormat;
+use crate::library::{Library, LibraryEntry};
+use crate::range::RangeFormat;
+use crate::simplification::SimplificationForm;
+use crate::state::{MemoryBudget, SessionState};
+use crate::translation::TranslationMethod;
+use crate::tree::{Street, TreeEditRule, TreeExportFormat};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use axum::extract::State;
+use axum::body::{Body, Bytes};
+use axum::extract::{FromRequest, Request, State};
+use axum::http::header;
+use axum::response::IntoResponse;
 use axum::routing::post;
 use axum::{Json, Router};
 use axum_embed::ServeEmbed;
 use clap::Parser;
+use futures_util::stream;
+use postflop_solver::Game;
 use rayon::ThreadPoolBuilder;
 use rust_embed::RustEmbed;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_json::json;
 use sysinfo::{System, SystemExt};
 use tokio::net::TcpListener;
 use tokio::signal;
+use tokio::sync::mpsc;
 
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
@@ -28,11 +59,25 @@ static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 #[folder = "../dist"]
 struct Assets;
 
+/// Number of chunks buffered between an export thread and the response body.
+const STREAM_CHANNEL_CAPACITY: usize = 16;
+
 #[derive(Serialize, Default)]
 struct Response {
     result: Value,
 }
 
+/// Responds with the value on success and with `{ "error": message }` on failure. As with the
+/// `Option<String>` results of the older endpoints, failures are reported in the body rather than
+/// with an error status.
+fn respond<T: Serialize>(result: Result<T, String>) -> Json<Response> {
+    let result = match result {
+        Ok(value) => json!(value),
+        Err(e) => json!({ "error": e }),
+    };
+    Json(Response { result })
+}
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Args {
@@ -43,13 +88,40 @@ struct Args {
     /// Port to listen to.
     #[arg(short, long, default_value_t = 7777)]
     port: u16,
+
+    /// Directory to store the range and configuration library in.
+    #[arg(short, long, default_value = "data")]
+    data_dir: PathBuf,
+
+    /// Maximum memory in MB that all solver games may allocate in total.
+    #[arg(long)]
+    max_memory: Option<u64>,
+
+    /// Maximum memory in MB that a single session's game may allocate.
+    #[arg(long)]
+    max_session_memory: Option<u64>,
 }
 
 #[tokio::main]
 async fn main() {
-    let Args { host, port } = Args::parse();
-
-    let global_session = Arc::new(SessionState::default());
+    let Args {
+        host,
+        port,
+        data_dir,
+        max_memory,
+        max_session_memory,
+    } = Args::parse();
+
+    // starting with an empty library would overwrite the unreadable one on the next save
+    let library = Library::open(&data_dir).unwrap_or_else(|e| {
+        eprintln!("failed to open library in {}: {e}", data_dir.display());
+        std::process::exit(1);
+    });
+    let memory_budget = Arc::new(MemoryBudget::new(
+        max_memory.map(|mb| mb << 20),
+        max_session_memory.map(|mb| mb << 20),
+    ));
+    let global_session = Arc::new(SessionState::new(library, memory_budget));
     let invoke_routes = Router::new()
         .route("/reset", post(reset))
         .route("/os_name", post(os_name))
@@ -64,6 +136,14 @@ async fn main() {
         .route("/range_to_string", post(range_to_string))
         .route("/range_get_weights", post(range_get_weights))
         .route("/range_raw_data", post(range_raw_data))
+        .route("/range_import", post(range_import))
+        .route("/range_export", post(range_export))
+        .route("/preset_list", post(preset_list))
+        .route("/preset_load", post(preset_load))
+        .route("/config_migrate", post(config_migrate))
+        .route("/config_validate", post(config_validate))
+        .route("/template_list", post(template_list))
+        .route("/template_apply", post(template_apply))
         .route("/tree_new", post(tree_new))
         .route("/tree_added_lines", post(tree_added_lines))
         .route("/tree_removed_lines", post(tree_removed_lines))
@@ -79,12 +159,20 @@ async fn main() {
         .route("/tree_remove_current_node", post(tree_remove_current_node))
         .route("/tree_delete_added_line", post(tree_delete_added_line))
         .route("/tree_delete_removed_line", post(tree_delete_removed_line))
+        .route("/tree_stats", post(tree_stats))
+        .route("/tree_export", post(tree_export))
+        .route("/tree_diff", post(tree_diff))
+        .route("/tree_apply_rules", post(tree_apply_rules))
+        .route("/tree_undo", post(tree_undo))
+        .route("/tree_redo", post(tree_redo))
+        .route("/tree_journal", post(tree_journal))
         .route("/bunching_init", post(bunching_init))
         .route("/bunching_clear", post(bunching_clear))
         .route("/bunching_progress", post(bunching_progress))
         .route("/game_init", post(game_init))
         .route("/game_private_cards", post(game_private_cards))
         .route("/game_memory_usage", post(game_memory_usage))
+        .route("/game_estimate_memory", post(game_estimate_memory))
         .route(
             "/game_memory_usage_bunching",
             post(game_memory_usage_bunching),
@@ -102,8 +190,34 @@ async fn main() {
         .route("/game_total_bet_amount", post(game_total_bet_amount))
         .route("/game_actions_after", post(game_actions_after))
         .route("/game_possible_cards", post(game_possible_cards))
+        .route("/game_line_to_history", post(game_line_to_history))
+        .route("/game_history_to_line", post(game_history_to_line))
+        .route("/game_replay_hand_history", post(game_replay_hand_history))
+        .route("/game_translate_action", post(game_translate_action))
+        .route("/game_sample_action", post(game_sample_action))
+        .route("/game_play_hand", post(game_play_hand))
+        .route("/game_evaluate_strategy", post(game_evaluate_strategy))
+        .route(
+            "/game_analyze_simplifications",
+            post(game_analyze_simplifications),
+        )
+        .route("/game_range_export", post(game_range_export))
         .route("/game_get_results", post(game_get_results))
         .route("/game_get_chance_reports", post(game_get_chance_reports))
+        .route("/game_export", post(game_export))
+        .route("/game_export_columnar", post(game_export_columnar))
+        .route("/trainer_start", post(trainer_start))
+        .route("/trainer_answer", post(trainer_answer))
+        .route("/trainer_stats", post(trainer_stats))
+        .route("/trainer_reset", post(trainer_reset))
+        .route("/library_get_array", post(library_get_array))
+        .route("/library_add_item", post(library_add_item))
+        .route("/library_add_group", post(library_add_group))
+        .route("/library_overwrite_item", post(library_overwrite_item))
+        .route("/library_rename_item", post(library_rename_item))
+        .route("/library_delete_item", post(library_delete_item))
+        .route("/library_import", post(library_import))
+        .route("/library_export", post(library_export))
         .with_state(global_session);
     let app = Router::new()
         .fallback_service(ServeEmbed::<Assets>::new())
@@ -144,10 +258,14 @@ async fn os_name() -> Json<Response> {
     })
 }
 
-async fn memory() -> Json<Response> {
+fn system_memory() -> (u64, u64) {
     let mut system = System::new_all();
     system.refresh_memory();
-    let result = (system.available_memory(), system.total_memory());
+    (system.available_memory(), system.total_memory())
+}
+
+async fn memory() -> Json<Response> {
+    let result = system_memory();
     Json(Response {
         result: json!(result),
     })
@@ -283,63 +401,107 @@ async fn range_raw_data(
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct TreeNewRequest {
-    board_len: i32,
-    starting_pot: i32,
-    effective_stack: i32,
-    donk_option: bool,
-    oop_flop_bet: String,
-    oop_flop_raise: String,
-    oop_turn_bet: String,
-    oop_turn_raise: String,
-    oop_turn_donk: String,
-    oop_river_bet: String,
-    oop_river_raise: String,
-    oop_river_donk: String,
-    ip_flop_bet: String,
-    ip_flop_raise: String,
-    ip_turn_bet: String,
-    ip_turn_raise: String,
-    ip_river_bet: String,
-    ip_river_raise: String,
-    add_allin_threshold: f64,
-    force_allin_threshold: f64,
-    merging_threshold: f64,
-    added_lines: String,
-    removed_lines: String,
+struct RangeImportRequest {
+    player: usize,
+    str: String,
+    format: RangeFormat,
+}
+
+async fn range_import(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<RangeImportRequest>,
+) -> Json<Response> {
+    let mut range_manager = state.range_manager.lock();
+    let result = crate::range::range_import(&mut range_manager, req.player, req.str, req.format);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RangeExportRequest {
+    player: usize,
+    format: RangeFormat,
+}
+
+async fn range_export(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<RangeExportRequest>,
+) -> Json<Response> {
+    let range_manager = state.range_manager.lock();
+    let result = crate::range::range_export(&range_manager, req.player, req.format);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+async fn preset_list() -> Json<Response> {
+    let result = crate::presets::preset_list();
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetLoadRequest {
+    player: usize,
+    id: String,
+}
+
+async fn preset_load(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<PresetLoadRequest>,
+) -> Json<Response> {
+    let mut range_manager = state.range_manager.lock();
+    let result = crate::presets::preset_load(&mut range_manager, req.player, req.id);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+async fn config_migrate(Json(VersionedConfig(config)): Json<VersionedConfig>) -> Json<Response> {
+    Json(Response {
+        result: json!(config),
+    })
+}
+
+async fn config_validate(Json(VersionedConfig(config)): Json<VersionedConfig>) -> Json<Response> {
+    let result = crate::config::config_validate(&config);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+async fn template_list() -> Json<Response> {
+    let result = crate::templates::template_list();
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateApplyRequest {
+    id: String,
+    config: VersionedConfig,
+}
+
+async fn template_apply(Json(req): Json<TemplateApplyRequest>) -> Json<Response> {
+    respond(crate::templates::template_apply(req.config.0, req.id))
 }
 
 async fn tree_new(
     State(state): State<Arc<SessionState>>,
-    Json(req): Json<TreeNewRequest>,
+    Json(VersionedConfig(config)): Json<VersionedConfig>,
 ) -> Json<Response> {
     let mut action_tree = state.action_tree.lock();
-    let result = crate::tree::tree_new(
-        &mut action_tree,
-        req.board_len,
-        req.starting_pot,
-        req.effective_stack,
-        req.donk_option,
-        req.oop_flop_bet,
-        req.oop_flop_raise,
-        req.oop_turn_bet,
-        req.oop_turn_raise,
-        req.oop_turn_donk,
-        req.oop_river_bet,
-        req.oop_river_raise,
-        req.oop_river_donk,
-        req.ip_flop_bet,
-        req.ip_flop_raise,
-        req.ip_turn_bet,
-        req.ip_turn_raise,
-        req.ip_river_bet,
-        req.ip_river_raise,
-        req.add_allin_threshold,
-        req.force_allin_threshold,
-        req.merging_threshold,
-        req.added_lines,
-        req.removed_lines,
-    );
+    let mut tree_origin = state.tree_origin.lock();
+    let result = crate::tree::tree_new(&mut action_tree, &mut tree_origin, &config);
+    if result {
+        state.tree_journal.lock().clear();
+    }
     Json(Response {
         result: json!(result),
     })
@@ -347,7 +509,8 @@ async fn tree_new(
 
 async fn tree_added_lines(State(state): State<Arc<SessionState>>) -> Json<Response> {
     let action_tree = state.action_tree.lock();
-    let result = crate::tree::tree_added_lines(&action_tree);
+    let tree_origin = state.tree_origin.lock();
+    let result = crate::tree::tree_added_lines(&action_tree, &tree_origin);
     Json(Response {
         result: json!(result),
     })
@@ -355,7 +518,8 @@ async fn tree_added_lines(State(state): State<Arc<SessionState>>) -> Json<Respon
 
 async fn tree_removed_lines(State(state): State<Arc<SessionState>>) -> Json<Response> {
     let action_tree = state.action_tree.lock();
-    let result = crate::tree::tree_removed_lines(&action_tree);
+    let tree_origin = state.tree_origin.lock();
+    let result = crate::tree::tree_removed_lines(&action_tree, &tree_origin);
     Json(Response {
         result: json!(result),
     })
@@ -451,13 +615,25 @@ async fn tree_add_bet_action(
     Json(req): Json<TreeAddBetActionRequest>,
 ) -> Json<Response> {
     let mut action_tree = state.action_tree.lock();
-    crate::tree::tree_add_bet_action(&mut action_tree, req.amount, req.is_raise);
+    let mut tree_journal = state.tree_journal.lock();
+    let operation = format!(
+        "Add {} {}",
+        if req.is_raise { "raise" } else { "bet" },
+        req.amount
+    );
+    tree_journal.record(&mut action_tree, operation, |tree| {
+        crate::tree::tree_add_bet_action(tree, req.amount, req.is_raise)
+    });
     Json(Default::default())
 }
 
 async fn tree_remove_current_node(State(state): State<Arc<SessionState>>) -> Json<Response> {
     let mut action_tree = state.action_tree.lock();
-    crate::tree::tree_remove_current_node(&mut action_tree);
+    let mut tree_journal = state.tree_journal.lock();
+    let operation = "Remove node".to_string();
+    tree_journal.record(&mut action_tree, operation, |tree| {
+        crate::tree::tree_remove_current_node(tree)
+    });
     Json(Default::default())
 }
 
@@ -472,7 +648,11 @@ async fn tree_delete_added_line(
     Json(req): Json<TreeDeleteLineRequest>,
 ) -> Json<Response> {
     let mut action_tree = state.action_tree.lock();
-    crate::tree::tree_delete_added_line(&mut action_tree, req.line);
+    let mut tree_journal = state.tree_journal.lock();
+    let operation = format!("Delete added line {}", req.line);
+    tree_journal.record(&mut action_tree, operation, |tree| {
+        crate::tree::tree_delete_added_line(tree, req.line)
+    });
     Json(Default::default())
 }
 
@@ -481,14 +661,98 @@ async fn tree_delete_removed_line(
     Json(req): Json<TreeDeleteLineRequest>,
 ) -> Json<Response> {
     let mut action_tree = state.action_tree.lock();
-    crate::tree::tree_delete_removed_line(&mut action_tree, req.line);
+    let mut tree_journal = state.tree_journal.lock();
+    let operation = format!("Delete removed line {}", req.line);
+    tree_journal.record(&mut action_tree, operation, |tree| {
+        crate::tree::tree_delete_removed_line(tree, req.line)
+    });
     Json(Default::default())
 }
 
+async fn tree_stats(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let mut action_tree = state.action_tree.lock();
+    let result = crate::tree::tree_stats(&mut action_tree);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeExportRequest {
+    format: TreeExportFormat,
+}
+
+async fn tree_export(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<TreeExportRequest>,
+) -> impl IntoResponse {
+    let mut action_tree = state.action_tree.lock();
+    let body = crate::tree::tree_export(&mut action_tree, req.format);
+    ([(header::CONTENT_TYPE, req.format.content_type())], body)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeDiffRequest {
+    base: VersionedConfig,
+    other: VersionedConfig,
+}
+
+async fn tree_diff(Json(req): Json<TreeDiffRequest>) -> Json<Response> {
+    let base = req.base.0.game_tree();
+    let other = req.other.0.game_tree();
+    respond(base.and_then(|base| other.map(|other| crate::tree::tree_diff(base, other))))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TreeApplyRulesRequest {
+    rules: Vec<TreeEditRule>,
+    commit: bool,
+}
+
+async fn tree_apply_rules(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<TreeApplyRulesRequest>,
+) -> Json<Response> {
+    let mut action_tree = state.action_tree.lock();
+    let mut tree_journal = state.tree_journal.lock();
+    let tree_origin = state.tree_origin.lock();
+    let operation = format!("Apply {} rule(s)", req.rules.len());
+    let result = tree_journal.record(&mut action_tree, operation, |tree| {
+        crate::tree::tree_apply_rules(tree, &tree_origin, &req.rules, req.commit)
+    });
+    respond(result)
+}
+
+async fn tree_undo(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let mut action_tree = state.action_tree.lock();
+    let mut tree_journal = state.tree_journal.lock();
+    respond(crate::tree::tree_undo(&mut action_tree, &mut tree_journal))
+}
+
+async fn tree_redo(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let mut action_tree = state.action_tree.lock();
+    let mut tree_journal = state.tree_journal.lock();
+    respond(crate::tree::tree_redo(&mut action_tree, &mut tree_journal))
+}
+
+async fn tree_journal(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let tree_journal = state.tree_journal.lock();
+    let result = crate::tree::tree_journal(&tree_journal);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct BunchingInitRequest {
+    #[serde(deserialize_with = "crate::cards::deserialize_cards")]
     board: Vec<u8>,
+    #[serde(default, deserialize_with = "crate::cards::deserialize_cards")]
+    dead_cards: Vec<u8>,
 }
 
 async fn bunching_init(
@@ -497,7 +761,12 @@ async fn bunching_init(
 ) -> Json<Response> {
     let range_manager = state.range_manager.lock();
     let mut bunching_data = state.bunching_data.lock();
-    let result = crate::bunching::bunching_init(&range_manager, &mut bunching_data, req.board);
+    let result = crate::bunching::bunching_init(
+        &range_manager,
+        &mut bunching_data,
+        req.board,
+        req.dead_cards,
+    );
     Json(Response {
         result: json!(result),
     })
@@ -518,82 +787,59 @@ async fn bunching_progress(State(state): State<Arc<SessionState>>) -> Json<Respo
     })
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct GameInitRequest {
-    board: Vec<u8>,
-    starting_pot: i32,
-    effective_stack: i32,
-    rake_rate: f64,
-    rake_cap: f64,
-    donk_option: bool,
-    oop_flop_bet: String,
-    oop_flop_raise: String,
-    oop_turn_bet: String,
-    oop_turn_raise: String,
-    oop_turn_donk: String,
-    oop_river_bet: String,
-    oop_river_raise: String,
-    oop_river_donk: String,
-    ip_flop_bet: String,
-    ip_flop_raise: String,
-    ip_turn_bet: String,
-    ip_turn_raise: String,
-    ip_river_bet: String,
-    ip_river_raise: String,
-    add_allin_threshold: f64,
-    force_allin_threshold: f64,
-    merging_threshold: f64,
-    added_lines: String,
-    removed_lines: String,
-}
-
 async fn game_init(
     State(state): State<Arc<SessionState>>,
-    Json(req): Json<GameInitRequest>,
+    Json(VersionedConfig(config)): Json<VersionedConfig>,
 ) -> Json<Response> {
     let range_manager = state.range_manager.lock();
     let mut post_flop_game = state.post_flop_game.lock();
-    let result = crate::solver::game_init(
-        &range_manager,
-        &mut post_flop_game,
-        req.board,
-        req.starting_pot,
-        req.effective_stack,
-        req.rake_rate,
-        req.rake_cap,
-        req.donk_option,
-        req.oop_flop_bet,
-        req.oop_flop_raise,
-        req.oop_turn_bet,
-        req.oop_turn_raise,
-        req.oop_turn_donk,
-        req.oop_river_bet,
-        req.oop_river_raise,
-        req.oop_river_donk,
-        req.ip_flop_bet,
-        req.ip_flop_raise,
-        req.ip_turn_bet,
-        req.ip_turn_raise,
-        req.ip_river_bet,
-        req.ip_river_raise,
-        req.add_allin_threshold,
-        req.force_allin_threshold,
-        req.merging_threshold,
-        req.added_lines,
-        req.removed_lines,
-    );
+    state
+        .memory_budget
+        .release(&mut state.reserved_memory.lock());
+    let result = crate::solver::game_init(&range_manager, &mut post_flop_game, &config);
+    *state.game_generation.lock() += 1;
+    state.trainer.lock().end_session();
     Json(Response {
         result: json!(result),
     })
 }
 
-async fn game_private_cards(State(state): State<Arc<SessionState>>) -> Json<Response> {
+/// A JSON body that may be left empty, for endpoints whose options all have defaults.
+struct OptionalJson<T>(T);
+
+impl<T: DeserializeOwned + Default, S: Send + Sync> FromRequest<S> for OptionalJson<T> {
+    type Rejection = Json<Response>;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| respond::<()>(Err(e.to_string())))?;
+        if body.is_empty() {
+            return Ok(Self(T::default()));
+        }
+        serde_json::from_slice(&body)
+            .map(Self)
+            .map_err(|e| respond::<()>(Err(e.to_string())))
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotationRequest {
+    #[serde(default)]
+    notation: bool,
+}
+
+async fn game_private_cards(
+    State(state): State<Arc<SessionState>>,
+    OptionalJson(req): OptionalJson<NotationRequest>,
+) -> Json<Response> {
     let post_flop_game = state.post_flop_game.lock();
-    let result = crate::solver::game_private_cards(&post_flop_game);
-    Json(Response {
-        result: json!(result),
-    })
+    let result = match req.notation {
+        false => json!(crate::solver::game_private_cards(&post_flop_game)),
+        true => json!(crate::solver::game_private_cards_notation(&post_flop_game)),
+    };
+    Json(Response { result })
 }
 
 async fn game_memory_usage(State(state): State<Arc<SessionState>>) -> Json<Response> {
@@ -604,6 +850,29 @@ async fn game_memory_usage(State(state): State<Arc<SessionState>>) -> Json<Respo
     })
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameEstimateMemoryRequest {
+    oop_range: Option<String>,
+    ip_range: Option<String>,
+    #[serde(flatten)]
+    config: VersionedConfig,
+}
+
+async fn game_estimate_memory(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameEstimateMemoryRequest>,
+) -> Json<Response> {
+    let range_manager = state.range_manager.lock();
+    let result = crate::solver::game_estimate_memory(
+        &range_manager,
+        [req.oop_range, req.ip_range],
+        system_memory(),
+        &req.config.0,
+    );
+    respond(result)
+}
+
 async fn game_memory_usage_bunching(State(state): State<Arc<SessionState>>) -> Json<Response> {
     let post_flop_game = state.post_flop_game.lock();
     let result = crate::solver::game_memory_usage_bunching(&post_flop_game);
@@ -623,14 +892,28 @@ async fn game_allocate_memory(
     Json(req): Json<GameAllocateMemoryRequest>,
 ) -> Json<Response> {
     let mut post_flop_game = state.post_flop_game.lock();
-    crate::solver::game_allocate_memory(&mut post_flop_game, req.enable_compression);
-    Json(Default::default())
+    let mut reserved_memory = state.reserved_memory.lock();
+    let result = crate::solver::game_allocate_memory(
+        &mut post_flop_game,
+        &state.memory_budget,
+        &mut reserved_memory,
+        req.enable_compression,
+    );
+    Json(Response {
+        result: json!(result),
+    })
 }
 
 async fn game_set_bunching(State(state): State<Arc<SessionState>>) -> Json<Response> {
     let bunching_data = state.bunching_data.lock();
     let mut post_flop_game = state.post_flop_game.lock();
-    let result = crate::solver::game_set_bunching(&bunching_data, &mut post_flop_game);
+    let mut reserved_memory = state.reserved_memory.lock();
+    let result = crate::solver::game_set_bunching(
+        &bunching_data,
+        &mut post_flop_game,
+        &state.memory_budget,
+        &mut reserved_memory,
+    );
     Json(Response {
         result: json!(result),
     })
@@ -648,8 +931,11 @@ async fn game_solve_step(
 ) -> Json<Response> {
     let post_flop_game = state.post_flop_game.lock();
     let thread_pool = state.thread_pool.lock();
-    crate::solver::game_solve_step(&post_flop_game, &thread_pool, req.current_iteration);
-    Json(Default::default())
+    let result =
+        crate::solver::game_solve_step(&post_flop_game, &thread_pool, req.current_iteration);
+    Json(Response {
+        result: json!(result),
+    })
 }
 
 #[derive(Deserialize)]
@@ -695,6 +981,7 @@ async fn game_finalize(State(state): State<Arc<SessionState>>) -> Json<Response>
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameApplyHistoryRequest {
+    #[serde(deserialize_with = "deserialize_history")]
     history: Vec<usize>,
 }
 
@@ -710,6 +997,7 @@ async fn game_apply_history(
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameTotalBetAmountRequest {
+    #[serde(deserialize_with = "deserialize_history")]
     append: Vec<isize>,
 }
 
@@ -727,6 +1015,7 @@ async fn game_total_bet_amount(
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameActionsAfterRequest {
+    #[serde(deserialize_with = "deserialize_history")]
     append: Vec<isize>,
 }
 
@@ -741,9 +1030,268 @@ async fn game_actions_after(
     })
 }
 
-async fn game_possible_cards(State(state): State<Arc<SessionState>>) -> Json<Response> {
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameLineToHistoryRequest {
+    line: String,
+    #[serde(default)]
+    notation: bool,
+}
+
+async fn game_line_to_history(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameLineToHistoryRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    let result = crate::solver::game_line_to_history(&mut post_flop_game, &req.line);
+    respond(result.map(|history| match req.notation {
+        false => json!(history),
+        true => json!(crate::solver::history_to_notation(
+            &mut post_flop_game,
+            &history
+        )),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameHistoryToLineRequest {
+    #[serde(deserialize_with = "deserialize_history")]
+    history: Vec<isize>,
+}
+
+async fn game_history_to_line(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameHistoryToLineRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::solver::game_history_to_line(
+        &mut post_flop_game,
+        req.history,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameTranslateActionRequest {
+    amount: i32,
+    #[serde(default)]
+    method: TranslationMethod,
+}
+
+async fn game_translate_action(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameTranslateActionRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::translation::game_translate_action(
+        &mut post_flop_game,
+        req.amount,
+        req.method,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameSampleActionRequest {
+    #[serde(default, deserialize_with = "deserialize_history")]
+    history: Vec<usize>,
+    hand: String,
+    seed: Option<u64>,
+}
+
+async fn game_sample_action(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameSampleActionRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::sampling::game_sample_action(
+        &mut post_flop_game,
+        &req.history,
+        &req.hand,
+        req.seed,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GamePlayHandRequest {
+    #[serde(default, deserialize_with = "deserialize_history")]
+    history: Vec<usize>,
+    oop_hand: String,
+    ip_hand: String,
+    seed: Option<u64>,
+}
+
+async fn game_play_hand(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GamePlayHandRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::sampling::game_play_hand(
+        &mut post_flop_game,
+        &req.history,
+        [&req.oop_hand, &req.ip_hand],
+        req.seed,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameEvaluateStrategyRequest {
+    #[serde(default, deserialize_with = "deserialize_history")]
+    history: Vec<usize>,
+    strategy: BTreeMap<String, Vec<f64>>,
+}
+
+async fn game_evaluate_strategy(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameEvaluateStrategyRequest>,
+) -> Json<Response> {
+    // the best response is computed on a full copy of the game
+    let result = tokio::task::spawn_blocking(move || {
+        let mut post_flop_game = state.post_flop_game.lock();
+        let mut reserved_memory = state.reserved_memory.lock();
+        crate::evaluation::game_evaluate_strategy(
+            &mut post_flop_game,
+            &state.memory_budget,
+            &mut reserved_memory,
+            &state.thread_pool.lock(),
+            &req.history,
+            &req.strategy,
+        )
+    })
+    .await;
+    respond(result.unwrap_or_else(|e| Err(e.to_string())))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameAnalyzeSimplificationsRequest {
+    #[serde(default, deserialize_with = "deserialize_history")]
+    history: Vec<usize>,
+    #[serde(default)]
+    forms: Vec<SimplificationForm>,
+}
+
+async fn game_analyze_simplifications(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameAnalyzeSimplificationsRequest>,
+) -> Json<Response> {
+    // each form is measured on a full copy of the game
+    let result = tokio::task::spawn_blocking(move || {
+        let mut post_flop_game = state.post_flop_game.lock();
+        let mut reserved_memory = state.reserved_memory.lock();
+        crate::simplification::game_analyze_simplifications(
+            &mut post_flop_game,
+            &state.memory_budget,
+            &mut reserved_memory,
+            &state.thread_pool.lock(),
+            &req.history,
+            &req.forms,
+        )
+    })
+    .await;
+    respond(result.unwrap_or_else(|e| Err(e.to_string())))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrainerStartRequest {
+    #[serde(default, deserialize_with = "deserialize_history")]
+    history: Vec<usize>,
+    hero: Option<usize>,
+    tolerance: Option<f64>,
+    seed: Option<u64>,
+}
+
+async fn trainer_start(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<TrainerStartRequest>,
+) -> Json<Response> {
+    let mut trainer = state.trainer.lock();
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::trainer::trainer_start(
+        &mut trainer,
+        &mut post_flop_game,
+        &req.history,
+        req.hero,
+        req.tolerance,
+        req.seed,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrainerAnswerRequest {
+    action: usize,
+}
+
+async fn trainer_answer(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<TrainerAnswerRequest>,
+) -> Json<Response> {
+    let mut trainer = state.trainer.lock();
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::trainer::trainer_answer(
+        &mut trainer,
+        &mut post_flop_game,
+        req.action,
+    ))
+}
+
+async fn trainer_stats(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let trainer = state.trainer.lock();
+    let result = crate::trainer::trainer_stats(&trainer);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+async fn trainer_reset(State(state): State<Arc<SessionState>>) -> Json<Response> {
+    let mut trainer = state.trainer.lock();
+    crate::trainer::trainer_reset(&mut trainer);
+    Json(Default::default())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameReplayHandHistoryRequest {
+    text: String,
+    hero: Option<String>,
+}
+
+async fn game_replay_hand_history(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameReplayHandHistoryRequest>,
+) -> Json<Response> {
+    let mut post_flop_game = state.post_flop_game.lock();
+    respond(crate::hand_history::game_replay_hand_history(
+        &mut post_flop_game,
+        &req.text,
+        req.hero,
+    ))
+}
+
+async fn game_possible_cards(
+    State(state): State<Arc<SessionState>>,
+    OptionalJson(req): OptionalJson<NotationRequest>,
+) -> Json<Response> {
     let post_flop_game = state.post_flop_game.lock();
     let result = crate::solver::game_possible_cards(&post_flop_game);
+    let result = match req.notation {
+        false => json!(result),
+        true => json!(crate::cards::mask_to_strings(result)),
+    };
+    Json(Response { result })
+}
+
+async fn game_range_export(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<RangeExportRequest>,
+) -> Json<Response> {
+    let post_flop_game = state.post_flop_game.lock();
+    let result = crate::solver::game_range_export(&post_flop_game, req.player, req.format);
     Json(Response {
         result: json!(result),
     })
@@ -760,8 +1308,11 @@ async fn game_get_results(State(state): State<Arc<SessionState>>) -> Json<Respon
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameGetChanceReportsRequest {
+    #[serde(deserialize_with = "deserialize_history")]
     append: Vec<isize>,
     num_actions: usize,
+    #[serde(default)]
+    notation: bool,
 }
 
 async fn game_get_chance_reports(
@@ -769,9 +1320,177 @@ async fn game_get_chance_reports(
     Json(req): Json<GameGetChanceReportsRequest>,
 ) -> Json<Response> {
     let mut post_flop_game = state.post_flop_game.lock();
+    let result = crate::solver::game_get_chance_reports(
+        &mut post_flop_game,
+        req.append,
+        req.num_actions,
+        req.notation,
+    );
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameExportRequest {
+    format: ExportFormat,
+    street: Option<Street>,
+    max_depth: Option<usize>,
+}
+
+/// Runs `f` on a blocking thread and streams the chunks it sends as the response body. The sender
+/// returns `false` once the client has gone away; an error aborts the body.
+fn stream_body(
+    f: impl FnOnce(&mut (dyn FnMut(Vec<u8>) -> bool + Send)) -> Result<(), String> + Send + 'static,
+) -> Body {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, String>>(STREAM_CHANNEL_CAPACITY);
+    tokio::task::spawn_blocking(move || {
+        let mut send = |chunk| tx.blocking_send(Ok(chunk)).is_ok();
+        if let Err(e) = f(&mut send) {
+            let _ = tx.blocking_send(Err(e));
+        }
+    });
+    Body::from_stream(stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }))
+}
+
+async fn game_export(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameExportRequest>,
+) -> Result<impl IntoResponse, Json<Response>> {
+    if !state.post_flop_game.lock().is_solved() {
+        return Err(respond::<()>(Err("Game is not solved".to_string())));
+    }
+    let body = stream_body(move |send| {
+        crate::export::game_export(&state, req.format, req.street, req.max_depth, send)
+    });
+    Ok(([(header::CONTENT_TYPE, req.format.content_type())], body))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GameExportColumnarRequest {
+    format: ColumnarFormat,
+    street: Option<Street>,
+    max_depth: Option<usize>,
+}
+
+async fn game_export_columnar(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<GameExportColumnarRequest>,
+) -> Result<impl IntoResponse, Json<Response>> {
+    if !state.post_flop_game.lock().is_solved() {
+        return Err(respond::<()>(Err("Game is not solved".to_string())));
+    }
+    let body = stream_body(move |send| {
+        crate::columnar::game_export_columnar(&state, req.format, req.street, req.max_depth, send)
+    });
+    Ok(([(header::CONTENT_TYPE, req.format.content_type())], body))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryStoreRequest {
+    store: String,
+}
+
+async fn library_get_array(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryStoreRequest>,
+) -> Json<Response> {
+    let library = state.library.lock();
+    let result = crate::library::library_get_array(&library, &req.store);
+    Json(Response {
+        result: json!(result),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryItemRequest {
+    store: String,
+    item: LibraryEntry,
+}
+
+async fn library_add_item(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryItemRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
+    let result = crate::library::library_add_item(&mut library, &req.store, req.item);
+    respond(result)
+}
+
+async fn library_add_group(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryItemRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
+    let result = crate::library::library_add_group(&mut library, &req.store, req.item);
+    respond(result)
+}
+
+async fn library_overwrite_item(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryItemRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
+    let result = crate::library::library_overwrite_item(&mut library, &req.store, req.item);
+    respond(result)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryRenameItemRequest {
+    store: String,
+    item: LibraryEntry,
+    new_name: String,
+}
+
+async fn library_rename_item(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryRenameItemRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
     let result =
-        crate::solver::game_get_chance_reports(&mut post_flop_game, req.append, req.num_actions);
+        crate::library::library_rename_item(&mut library, &req.store, req.item, req.new_name);
+    respond(result)
+}
+
+async fn library_delete_item(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryItemRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
+    let result = crate::library::library_delete_item(&mut library, &req.store, req.item);
+    respond(result)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryImportRequest {
+    store: String,
+    data: Value,
+}
+
+async fn library_import(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryImportRequest>,
+) -> Json<Response> {
+    let mut library = state.library.lock();
+    let result = crate::library::library_import(&mut library, &req.store, req.data);
     Json(Response {
         result: json!(result),
     })
 }
+
+async fn library_export(
+    State(state): State<Arc<SessionState>>,
+    Json(req): Json<LibraryStoreRequest>,
+) -> Json<Response> {
+    let library = state.library.lock();
+    let result = crate::library::library_export(&library, &req.store);
+    Json(Response { result })
+}
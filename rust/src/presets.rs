@@ -0,0 +1,66 @@
+use crate::range::RangeManager;
+
+use postflop_solver::Range;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+#[derive(Serialize, Deserialize)]
+pub struct Preset {
+    id: String,
+    table: String,
+    depth: i32,
+    position: String,
+    action: String,
+    vs: Option<String>,
+    range: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PresetLibrary {
+    version: u32,
+    presets: Vec<Preset>,
+}
+
+static PRESETS: LazyLock<PresetLibrary> =
+    LazyLock::new(|| serde_json::from_str(include_str!("../presets/preflop.json")).unwrap());
+
+pub fn preset_list() -> &'static PresetLibrary {
+    &PRESETS
+}
+
+pub fn preset_load(range_state: &mut RangeManager, player: usize, id: String) -> Option<String> {
+    if player >= range_state.0.len() {
+        return Some(format!("Invalid player: {player}"));
+    }
+
+    let Some(preset) = PRESETS.presets.iter().find(|p| p.id == id) else {
+        return Some(format!("Preset not found: {id}"));
+    };
+
+    match Range::from_sanitized_str(&preset.range) {
+        Ok(range) => {
+            (range_state.0)[player] = range;
+            None
+        }
+        Err(e) => Some(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn bundled_presets_parse() {
+        let presets = &preset_list().presets;
+        assert!(!presets.is_empty());
+
+        let mut ids = HashSet::new();
+        for preset in presets {
+            assert!(ids.insert(&preset.id), "duplicate id: {}", preset.id);
+            let range = Range::from_sanitized_str(&preset.range);
+            assert!(range.is_ok(), "{}: {:?}", preset.id, range.err());
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::cmp::Ordering;
 
 use postflop_solver::*;
+use serde::Deserialize;
 
 #[derive(Default)]
 pub struct RangeManager(pub [Range; 6]);
@@ -74,3 +75,293 @@ pub fn range_raw_data(range_state: &RangeManager, player: usize) -> Vec<f32> {
     let range = &(range_state.0)[player];
     range.raw_data().to_vec()
 }
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RangeFormat {
+    Pio,
+    Csv,
+}
+
+const RANKS: &[u8; 13] = b"23456789TJQKA";
+
+/// Name of the 13x13 cell of a combo, e.g. `AKs`, `T9o` or `77`.
+pub fn hand_class(c1: Card, c2: Card) -> String {
+    let (high, low) = (c1.max(c2), c1.min(c2));
+    let (rank1, rank2) = (
+        RANKS[high as usize / 4] as char,
+        RANKS[low as usize / 4] as char,
+    );
+    match (rank1 == rank2, high % 4 == low % 4) {
+        (true, _) => format!("{rank1}{rank2}"),
+        (false, true) => format!("{rank1}{rank2}s"),
+        (false, false) => format!("{rank1}{rank2}o"),
+    }
+}
+
+#[inline]
+fn combo_to_string(index: usize) -> String {
+    let (c1, c2) = index_to_card_pair(index);
+    card_to_string(c2).unwrap() + &card_to_string(c1).unwrap()
+}
+
+#[inline]
+fn combo_from_str(s: &str) -> Option<usize> {
+    if s.len() != 4 || !s.is_ascii() {
+        return None;
+    }
+    let c1 = card_from_str(&s[0..2]).ok()?;
+    let c2 = card_from_str(&s[2..4]).ok()?;
+    (c1 != c2).then(|| card_pair_to_index(c1, c2))
+}
+
+#[inline]
+fn weight_from_str(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(w) if (0.0..=1.0).contains(&w) => Ok(w),
+        _ => Err(format!("Invalid weight: {s}")),
+    }
+}
+
+/// Indices of the combos of a hand class, e.g. `AKs` for `rank1 = 12, rank2 = 11, suited`.
+fn class_combos(rank1: u8, rank2: u8, suited: bool) -> Vec<usize> {
+    let mut combos = Vec::new();
+    for suit1 in 0..4 {
+        for suit2 in 0..4 {
+            let (c1, c2) = (4 * rank1 + suit1, 4 * rank2 + suit2);
+            let valid = match rank1 == rank2 {
+                true => suit1 < suit2,
+                false => (suit1 == suit2) == suited,
+            };
+            if valid {
+                combos.push(card_pair_to_index(c1, c2));
+            }
+        }
+    }
+    combos
+}
+
+fn parse_pio(str: &str) -> Result<Vec<f32>, String> {
+    let mut weights = vec![0.0; 1326];
+    let mut bracket_weight = None;
+
+    for token in str.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let mut token = token;
+
+        // GTO+ style block: `[50]AA,KK[/50]` (weights in percent)
+        if let Some(rest) = token.strip_prefix('[') {
+            let (weight, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| format!("Unclosed bracket: {token}"))?;
+            let weight = weight.trim().parse::<f32>().ok();
+            match weight {
+                Some(w) if (0.0..=100.0).contains(&w) => bracket_weight = Some(w / 100.0),
+                _ => return Err(format!("Invalid bracket weight: {token}")),
+            }
+            token = rest.trim();
+        }
+        let mut close_bracket = false;
+        if let Some(pos) = token.find("[/") {
+            if bracket_weight.is_none() {
+                return Err(format!("Unexpected closing bracket: {token}"));
+            }
+            close_bracket = true;
+            token = token[..pos].trim();
+        }
+
+        let (hand, weight) = match token.split_once(':') {
+            Some((hand, weight)) => (hand.trim(), weight_from_str(weight.trim())?),
+            None => (token, bracket_weight.unwrap_or(1.0)),
+        };
+
+        if let Some(index) = combo_from_str(hand) {
+            weights[index] = weight;
+        } else {
+            let mask = Range::from_sanitized_str(hand)?;
+            for (w, &m) in weights.iter_mut().zip(mask.raw_data()) {
+                if m > 0.0 {
+                    *w = weight;
+                }
+            }
+        }
+
+        if close_bracket {
+            bracket_weight = None;
+        }
+    }
+
+    Ok(weights)
+}
+
+fn parse_csv(str: &str) -> Result<Vec<f32>, String> {
+    let mut weights = vec![0.0; 1326];
+
+    for (line_index, line) in str.lines().map(str::trim).enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let (hand, weight) = line
+            .split_once(',')
+            .ok_or_else(|| format!("Invalid line: {line}"))?;
+        let (hand, weight) = (hand.trim(), weight.trim());
+        match combo_from_str(hand) {
+            Some(index) => weights[index] = weight_from_str(weight)?,
+            None if line_index == 0 => continue, // header
+            None => return Err(format!("Invalid hand: {hand}")),
+        }
+    }
+
+    Ok(weights)
+}
+
+fn format_pio(weights: &[f32]) -> String {
+    let mut tokens = Vec::new();
+
+    for rank1 in (0..13).rev() {
+        for rank2 in (0..=rank1).rev() {
+            for suited in [true, false] {
+                if rank1 == rank2 && !suited {
+                    continue;
+                }
+
+                let combos = class_combos(rank1, rank2, suited);
+                let first = weights[combos[0]];
+                if combos.iter().all(|&i| weights[i] == first) {
+                    if first > 0.0 {
+                        let mut class = format!(
+                            "{}{}",
+                            RANKS[rank1 as usize] as char, RANKS[rank2 as usize] as char
+                        );
+                        if rank1 != rank2 {
+                            class.push(if suited { 's' } else { 'o' });
+                        }
+                        tokens.push(match first {
+                            1.0 => class,
+                            w => format!("{class}:{w}"),
+                        });
+                    }
+                } else {
+                    for &index in &combos {
+                        match weights[index] {
+                            0.0 => {}
+                            1.0 => tokens.push(combo_to_string(index)),
+                            w => tokens.push(format!("{}:{w}", combo_to_string(index))),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    tokens.join(",")
+}
+
+fn format_csv(weights: &[f32]) -> String {
+    let mut out = String::new();
+    for (index, &weight) in weights.iter().enumerate() {
+        out.push_str(&format!("{},{weight}\n", combo_to_string(index)));
+    }
+    out
+}
+
+pub fn format_weights(weights: &[f32], format: RangeFormat) -> String {
+    match format {
+        RangeFormat::Pio => format_pio(weights),
+        RangeFormat::Csv => format_csv(weights),
+    }
+}
+
+pub fn range_import(
+    range_state: &mut RangeManager,
+    player: usize,
+    str: String,
+    format: RangeFormat,
+) -> Option<String> {
+    let weights = match format {
+        RangeFormat::Pio => parse_pio(&str),
+        RangeFormat::Csv => parse_csv(&str),
+    };
+    match weights.and_then(|w| Range::from_raw_data(&w)) {
+        Ok(range) => {
+            (range_state.0)[player] = range;
+            None
+        }
+        Err(e) => Some(e),
+    }
+}
+
+pub fn range_export(range_state: &RangeManager, player: usize, format: RangeFormat) -> String {
+    let range = &(range_state.0)[player];
+    format_weights(range.raw_data(), format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(s: &str) -> usize {
+        combo_from_str(s).unwrap()
+    }
+
+    #[test]
+    fn parse_pio_combos_and_weights() {
+        let weights = parse_pio("AsKs, QhJh:0.5").unwrap();
+        assert_eq!(weights[combo("AsKs")], 1.0);
+        assert_eq!(weights[combo("QhJh")], 0.5);
+        assert_eq!(weights.iter().filter(|&&w| w > 0.0).count(), 2);
+    }
+
+    #[test]
+    fn parse_pio_bracket_block() {
+        let weights = parse_pio("[50]AsKs,AhKh[/50],AdKd").unwrap();
+        assert_eq!(weights[combo("AsKs")], 0.5);
+        assert_eq!(weights[combo("AhKh")], 0.5);
+        assert_eq!(weights[combo("AdKd")], 1.0);
+    }
+
+    #[test]
+    fn parse_pio_rejects_malformed_input() {
+        assert!(parse_pio("[50AsKs").is_err());
+        assert!(parse_pio("[150]AsKs[/150]").is_err());
+        assert!(parse_pio("AsKs[/50]").is_err());
+        assert!(parse_pio("AsKs:1.5").is_err());
+    }
+
+    #[test]
+    fn pio_round_trip() {
+        let mut weights = vec![0.0; 1326];
+        for index in class_combos(12, 12, false) {
+            weights[index] = 1.0;
+        }
+        for index in class_combos(12, 11, true) {
+            weights[index] = 0.25;
+        }
+        weights[combo("7h6h")] = 0.5;
+
+        let text = format_pio(&weights);
+        assert_eq!(text, "AA,AKs:0.25,7h6h:0.5");
+        assert_eq!(parse_pio(&text).unwrap(), weights);
+    }
+
+    #[test]
+    fn parse_csv_skips_header() {
+        let weights = parse_csv("hand,weight\nAsKs,1\n\nQhJh,0.25\n").unwrap();
+        assert_eq!(weights[combo("AsKs")], 1.0);
+        assert_eq!(weights[combo("QhJh")], 0.25);
+    }
+
+    #[test]
+    fn parse_csv_rejects_malformed_input() {
+        assert!(parse_csv("AsKs,1\nQhJh").is_err());
+        assert!(parse_csv("AsKs,1\nXxYy,1").is_err());
+        assert!(parse_csv("AsKs,2").is_err());
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut weights = vec![0.0; 1326];
+        weights[combo("AsKs")] = 1.0;
+        weights[combo("2c2d")] = 0.75;
+        assert_eq!(parse_csv(&format_csv(&weights)).unwrap(), weights);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::cards::parse_hand;
+use crate::solver::{history_to_line, round, try_apply_history};
+use crate::tree::encode_action;
+
+use postflop_solver::*;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SplitMix64. Small and stable across versions, so a seed always reproduces the same hand.
+#[derive(Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+pub fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(|| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        now.as_nanos() as u64
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampledAction {
+    pub index: usize,
+    action: String,
+    probability: f64,
+    actions: Vec<String>,
+    strategy: Vec<f64>,
+    action_ev: Vec<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SampleResponse {
+    seed: u64,
+    #[serde(flatten)]
+    sampled: SampledAction,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PlayStep {
+    Action {
+        player: usize,
+        #[serde(flatten)]
+        sampled: SampledAction,
+    },
+    Card {
+        card: String,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayedHand {
+    seed: u64,
+    line: String,
+    history: Vec<usize>,
+    steps: Vec<PlayStep>,
+    total_bet_amount: [i32; 2],
+}
+
+pub fn hand_index(game: &PostFlopGame, player: usize, hand: (Card, Card)) -> Result<usize, String> {
+    game.private_cards(player)
+        .iter()
+        .position(|&cards| cards == hand)
+        .ok_or_else(|| "Hand conflicts with the board".to_string())
+}
+
+/// Samples an action for the hand at `index` of the current player.
+pub fn sample_action(
+    game: &mut PostFlopGame,
+    index: usize,
+    rng: &mut Rng,
+) -> Result<SampledAction, String> {
+    let player = game.current_player();
+    if game.weights(player)[index] <= 0.0 {
+        return Err("Hand is not in range at this node".to_string());
+    }
+
+    let actions = game.available_actions();
+    let num_hands = game.private_cards(player).len();
+    let strategy = game.strategy();
+    let hand_strategy = (0..actions.len())
+        .map(|i| strategy[i * num_hands + index])
+        .collect::<Vec<_>>();
+
+    game.cache_normalized_weights();
+    let ev = game.expected_values_detail(player);
+    let action_ev = (0..actions.len())
+        .map(|i| round(ev[i * num_hands + index] as f64))
+        .collect();
+
+    // the strategy may not sum to exactly one, so scale the draw instead of normalizing
+    let sum = hand_strategy.iter().map(|&p| p as f64).sum::<f64>();
+    let mut draw = rng.next_f64() * sum;
+    let mut chosen = actions.len() - 1;
+    for (i, &p) in hand_strategy.iter().enumerate() {
+        if p > 0.0 {
+            chosen = i;
+            draw -= p as f64;
+            if draw < 0.0 {
+                break;
+            }
+        }
+    }
+
+    Ok(SampledAction {
+        index: chosen,
+        action: encode_action(actions[chosen]),
+        probability: round(hand_strategy[chosen] as f64),
+        actions: actions.into_iter().map(encode_action).collect(),
+        strategy: hand_strategy.iter().map(|&p| round(p as f64)).collect(),
+        action_ev,
+    })
+}
+
+fn sample_at(game: &mut PostFlopGame, hand: &str, rng: &mut Rng) -> Result<SampledAction, String> {
+    if game.is_terminal_node() || game.is_chance_node() {
+        return Err("Node is not a decision node".to_string());
+    }
+    let index = hand_index(game, game.current_player(), parse_hand(hand)?)?;
+    sample_action(game, index, rng)
+}
+
+/// Samples an action for `hand` at the node reached by `history`, following the solved strategy.
+pub fn game_sample_action(
+    game_state: &mut PostFlopGame,
+    history: &[usize],
+    hand: &str,
+    seed: Option<u64>,
+) -> Result<SampleResponse, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let seed = resolve_seed(seed);
+    let mut rng = Rng::new(seed);
+    let saved = game_state.history().to_vec();
+    try_apply_history(game_state, history)?;
+    let result = sample_at(game_state, hand, &mut rng);
+    game_state.apply_history(&saved);
+    Ok(SampleResponse {
+        seed,
+        sampled: result?,
+    })
+}
+
+fn play_hand(
+    game: &mut PostFlopGame,
+    hands: [(Card, Card); 2],
+    rng: &mut Rng,
+) -> Result<Vec<PlayStep>, String> {
+    let indices = [
+        hand_index(game, 0, hands[0])?,
+        hand_index(game, 1, hands[1])?,
+    ];
+    let blocked = hands
+        .iter()
+        .fold(0, |mask, &(c1, c2)| mask | 1 << c1 | 1 << c2);
+
+    let mut steps = Vec::new();
+    while !game.is_terminal_node() {
+        if game.is_chance_node() {
+            let possible_cards = game.possible_cards() & !blocked;
+            let cards = (0..52)
+                .filter(|&card| possible_cards & (1 << card) != 0)
+                .collect::<Vec<_>>();
+            if cards.is_empty() {
+                return Err("No card can be dealt".to_string());
+            }
+            let card = cards[(rng.next_u64() % cards.len() as u64) as usize];
+            steps.push(PlayStep::Card {
+                card: card_to_string(card as Card).unwrap(),
+            });
+            game.play(card);
+        } else {
+            let player = game.current_player();
+            let sampled = sample_action(game, indices[player], rng)?;
+            game.play(sampled.index);
+            steps.push(PlayStep::Action { player, sampled });
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Plays the rest of the hand from `history` with both players following the solved strategy.
+/// Chance cards are dealt uniformly from the cards not held by either player.
+pub fn game_play_hand(
+    game_state: &mut PostFlopGame,
+    history: &[usize],
+    hands: [&str; 2],
+    seed: Option<u64>,
+) -> Result<PlayedHand, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let hands = [parse_hand(hands[0])?, parse_hand(hands[1])?];
+    let (oop, ip) = (hands[0], hands[1]);
+    if [oop.0, oop.1].iter().any(|&c| c == ip.0 || c == ip.1) {
+        return Err("Hands overlap".to_string());
+    }
+
+    let seed = resolve_seed(seed);
+    let mut rng = Rng::new(seed);
+    let saved = game_state.history().to_vec();
+    try_apply_history(game_state, history)?;
+    let result = play_hand(game_state, hands, &mut rng).map(|steps| {
+        let history = game_state.history().to_vec();
+        let total_bet_amount = game_state.total_bet_amount();
+        (steps, history, total_bet_amount)
+    });
+    game_state.apply_history(&saved);
+
+    let (steps, history, total_bet_amount) = result?;
+    let line = history_to_line(
+        game_state,
+        &history.iter().map(|&i| i as isize).collect::<Vec<_>>(),
+    )?;
+    Ok(PlayedHand {
+        seed,
+        line,
+        history,
+        steps,
+        total_bet_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cards::{cards_to_mask, parse_cards};
+    use crate::solver::tests::{build_game, solved_river_game};
+
+    const RANGES: [&str; 2] = ["AK,QQ,76s", "KK-TT,A5s,K9s"];
+
+    #[test]
+    fn fixed_seed_reproduces_the_action() {
+        let mut game = solved_river_game(RANGES);
+        let first = game_sample_action(&mut game, &[], "AhKd", Some(42)).unwrap();
+        let second = game_sample_action(&mut game, &[], "AhKd", Some(42)).unwrap();
+        assert_eq!(first.seed, 42);
+        assert_eq!(first.sampled.index, second.sampled.index);
+
+        let first = game_play_hand(&mut game, &[], ["AhKd", "JsJc"], Some(7)).unwrap();
+        let second = game_play_hand(&mut game, &[], ["AhKd", "JsJc"], Some(7)).unwrap();
+        assert_eq!(first.history, second.history);
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn sampled_frequencies_follow_the_strategy() {
+        let mut game = solved_river_game(RANGES);
+        let index = hand_index(&game, 0, parse_hand("AhKd").unwrap()).unwrap();
+        let num_hands = game.private_cards(0).len();
+        let strategy = game.strategy();
+        let expected = (0..game.available_actions().len())
+            .map(|i| strategy[i * num_hands + index] as f64)
+            .collect::<Vec<_>>();
+
+        let mut rng = Rng::new(1);
+        let mut counts = vec![0; expected.len()];
+        let draws = 20000;
+        for _ in 0..draws {
+            let sampled = sample_action(&mut game, index, &mut rng).unwrap();
+            assert_eq!(sampled.probability, round(expected[sampled.index]));
+            counts[sampled.index] += 1;
+        }
+        let sum = expected.iter().sum::<f64>();
+        for (count, p) in counts.into_iter().zip(expected) {
+            let frequency = count as f64 / draws as f64;
+            assert!((frequency - p / sum).abs() < 0.02, "{frequency} vs {p}");
+        }
+    }
+
+    #[test]
+    fn hands_without_reach_are_rejected() {
+        let mut game = build_game("Ts7h2cKc5d", RANGES);
+        let index = hand_index(&game, 0, parse_hand("AhKd").unwrap()).unwrap();
+        let num_hands = game.private_cards(0).len();
+
+        // AhKd always bets half pot, so it never reaches the node after check and a bet
+        let mut locked = vec![0.0; game.available_actions().len() * num_hands];
+        locked[num_hands + index] = 1.0;
+        game.lock_current_strategy(&locked);
+        solve(&mut game, 1000, 0.1, false);
+
+        let result = game_sample_action(&mut game, &[0, 1], "AhKd", Some(0));
+        assert!(result.is_err());
+        assert!(game_sample_action(&mut game, &[1], "JsJc", Some(0)).is_ok());
+    }
+
+    #[test]
+    fn play_out_deals_only_live_cards() {
+        let mut game = build_game("Ts7h2cKc", RANGES);
+        solve(&mut game, 200, 0.5, false);
+        let hands = ["AhKd", "JsJc"];
+        let blocked = cards_to_mask(&parse_cards("Ts7h2cKcAhKdJsJc").unwrap());
+
+        for seed in 0..20 {
+            let played = game_play_hand(&mut game, &[], hands, Some(seed)).unwrap();
+            let cards = played
+                .steps
+                .iter()
+                .filter_map(|step| match step {
+                    PlayStep::Card { card } => Some(card_from_str(card).unwrap()),
+                    PlayStep::Action { .. } => None,
+                })
+                .collect::<Vec<_>>();
+            // both players check the turn, so the river is always dealt
+            assert_eq!(cards.len(), 1);
+            assert_eq!(blocked & 1 << cards[0], 0);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use crate::solver::{round, try_apply_history, with_locked_copy};
+use crate::state::MemoryBudget;
+
+use postflop_solver::*;
+use rayon::ThreadPool;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SimplificationForm {
+    /// Each combo always takes its most frequent action.
+    Pure,
+    /// Frequencies rounded to multiples of `step`, e.g. 0.25 for 0/25/50/75/100%.
+    Bucketed { step: f64 },
+    /// Only the `max_sizes` most used bet or raise sizes of each node are kept. The frequency of
+    /// the others moves to the closest kept size.
+    FewerSizes { max_sizes: usize },
+}
+
+const DEFAULT_FORMS: [SimplificationForm; 3] = [
+    SimplificationForm::Pure,
+    SimplificationForm::Bucketed { step: 0.25 },
+    SimplificationForm::FewerSizes { max_sizes: 1 },
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimplificationResult {
+    form: SimplificationForm,
+    exploitability: f64,
+    /// Increase of the exploitability over the unsimplified solution.
+    cost: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimplificationReport {
+    /// Exploitability of the solution, as reported by the engine.
+    exploitability: f64,
+    /// Decision nodes the forms are applied to.
+    num_nodes: usize,
+    results: Vec<SimplificationResult>,
+}
+
+fn simplify_pure(strategy: &mut [f32], num_actions: usize) {
+    let num_hands = strategy.len() / num_actions;
+    for hand in 0..num_hands {
+        let best = (0..num_actions)
+            .max_by(|&a, &b| {
+                strategy[a * num_hands + hand].total_cmp(&strategy[b * num_hands + hand])
+            })
+            .unwrap();
+        for action in 0..num_actions {
+            strategy[action * num_hands + hand] = (action == best) as i32 as f32;
+        }
+    }
+}
+
+fn simplify_bucketed(strategy: &mut [f32], num_actions: usize, step: f64) {
+    let num_hands = strategy.len() / num_actions;
+    for hand in 0..num_hands {
+        let freqs = (0..num_actions)
+            .map(|action| strategy[action * num_hands + hand] as f64)
+            .collect::<Vec<_>>();
+        let mut rounded = freqs
+            .iter()
+            .map(|&f| (f / step).round() * step)
+            .collect::<Vec<_>>();
+        let largest = |rounded: &[f64]| {
+            (0..num_actions)
+                .max_by(|&a, &b| {
+                    rounded[a]
+                        .total_cmp(&rounded[b])
+                        .then(freqs[a].total_cmp(&freqs[b]))
+                })
+                .unwrap()
+        };
+
+        // take whole steps off the largest buckets while rounding up overshoots, then put the
+        // remainder on the largest one, which stays on the grid whenever 1 is a multiple of `step`
+        while rounded.iter().sum::<f64>() > 1.0 + 1e-9 {
+            let index = largest(&rounded);
+            rounded[index] = (rounded[index] - step).max(0.0);
+        }
+        let index = largest(&rounded);
+        rounded[index] += 1.0 - rounded.iter().sum::<f64>();
+
+        for action in 0..num_actions {
+            strategy[action * num_hands + hand] = rounded[action] as f32;
+        }
+    }
+}
+
+fn simplify_sizes(actions: &[Action], weights: &[f32], strategy: &mut [f32], max_sizes: usize) {
+    let num_actions = actions.len();
+    let num_hands = strategy.len() / num_actions;
+    let sizes = actions
+        .iter()
+        .enumerate()
+        .filter_map(|(i, action)| match action {
+            Action::Bet(amount) | Action::Raise(amount) | Action::AllIn(amount) => {
+                Some((i, *amount))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if sizes.len() <= max_sizes {
+        return;
+    }
+
+    // rank the sizes by their frequency over the range reaching the node
+    let usage = |action: usize| {
+        (0..num_hands)
+            .map(|hand| (strategy[action * num_hands + hand] * weights[hand]) as f64)
+            .sum::<f64>()
+    };
+    let mut ranked = sizes.clone();
+    ranked.sort_by(|a, b| usage(b.0).total_cmp(&usage(a.0)));
+    let kept = &ranked[..max_sizes];
+
+    for &(action, amount) in &ranked[max_sizes..] {
+        let &(target, _) = kept
+            .iter()
+            .min_by_key(|&&(_, kept_amount)| (kept_amount - amount).abs())
+            .unwrap();
+        for hand in 0..num_hands {
+            strategy[target * num_hands + hand] += strategy[action * num_hands + hand];
+            strategy[action * num_hands + hand] = 0.0;
+        }
+    }
+}
+
+/// Strategy of the current node in the simplified form.
+fn simplify(game: &PostFlopGame, form: SimplificationForm) -> Vec<f32> {
+    let actions = game.available_actions();
+    let mut strategy = game.strategy();
+    match form {
+        SimplificationForm::Pure => simplify_pure(&mut strategy, actions.len()),
+        SimplificationForm::Bucketed { step } => {
+            simplify_bucketed(&mut strategy, actions.len(), step)
+        }
+        SimplificationForm::FewerSizes { max_sizes } => {
+            let weights = game.weights(game.current_player());
+            simplify_sizes(&actions, weights, &mut strategy, max_sizes)
+        }
+    }
+    strategy
+}
+
+fn analyze(
+    game: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    forms: &[SimplificationForm],
+) -> Result<SimplificationReport, String> {
+    for form in forms {
+        match *form {
+            SimplificationForm::Bucketed { step } if !(step > 0.0 && step <= 1.0) => {
+                return Err(format!("Invalid step: {step}"));
+            }
+            SimplificationForm::FewerSizes { max_sizes: 0 } => {
+                return Err("At least one size must be kept".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    if game.is_terminal_node() || game.is_chance_node() {
+        return Err("Node is not a decision node".to_string());
+    }
+
+    let baseline = pool.install(|| compute_exploitability(game)) as f64;
+    let mut num_nodes = 0;
+    let mut results = Vec::new();
+    for &form in forms {
+        let mut replace = |game: &PostFlopGame| Some(simplify(game, form));
+        let (exploitability, count) = with_locked_copy(
+            game,
+            memory_budget,
+            reserved_memory,
+            pool,
+            &mut replace,
+            |copy, count| (compute_exploitability(copy) as f64, count),
+        )?;
+        num_nodes = count;
+        results.push(SimplificationResult {
+            form,
+            exploitability: round(exploitability),
+            cost: round(exploitability - baseline),
+        });
+    }
+
+    Ok(SimplificationReport {
+        exploitability: round(baseline),
+        num_nodes,
+        results,
+    })
+}
+
+/// Measures how much exploitability each simplified form of the strategy costs. The forms are
+/// applied to every decision node below the node reached by `history`, on all streets, in a
+/// locked copy of the game whose exploitability the engine computes, so the solution is left
+/// unchanged. Without `forms`, pure strategies, 25% buckets and a single bet size are compared.
+pub fn game_analyze_simplifications(
+    game_state: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    history: &[usize],
+    forms: &[SimplificationForm],
+) -> Result<SimplificationReport, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let forms = match forms.is_empty() {
+        true => &DEFAULT_FORMS[..],
+        false => forms,
+    };
+    let saved = game_state.history().to_vec();
+    try_apply_history(game_state, history)?;
+    let result = analyze(game_state, memory_budget, reserved_memory, pool, forms);
+    game_state.apply_history(&saved);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::solved_river_game;
+    use rayon::ThreadPoolBuilder;
+
+    fn analyze_at(
+        game: &mut PostFlopGame,
+        history: &[usize],
+        forms: &[SimplificationForm],
+    ) -> SimplificationReport {
+        let pool = ThreadPoolBuilder::new().build().unwrap();
+        let memory_budget = MemoryBudget::new(None, None);
+        let mut reserved_memory = 0;
+        let result = game_analyze_simplifications(
+            game,
+            &memory_budget,
+            &mut reserved_memory,
+            &pool,
+            history,
+            forms,
+        );
+        assert_eq!(reserved_memory, 0);
+        result.unwrap()
+    }
+
+    #[test]
+    fn buckets_stay_on_the_grid() {
+        // a hand per column: 40/35/25%, 20% everywhere, 60/40/0%
+        let mut strategy = [
+            0.4, 0.2, 0.6, 0.35, 0.2, 0.4, 0.25, 0.2, 0.0, 0.0, 0.2, 0.0, 0.0, 0.2, 0.0,
+        ];
+        simplify_bucketed(&mut strategy, 5, 0.25);
+        for hand in 0..3 {
+            let freqs = (0..5).map(|a| strategy[a * 3 + hand]).collect::<Vec<_>>();
+            assert_eq!(freqs.iter().sum::<f32>(), 1.0, "{freqs:?}");
+            assert!(
+                freqs.iter().all(|&f| f >= 0.0 && (f * 4.0).fract() == 0.0),
+                "{freqs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn unmodified_strategy_matches_the_exploitability() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        // every node has at most three sizes, so keeping three changes nothing
+        let report = analyze_at(
+            &mut game,
+            &[],
+            &[SimplificationForm::FewerSizes { max_sizes: 3 }],
+        );
+        let exploitability = compute_exploitability(&game) as f64;
+        assert_eq!(report.exploitability, round(exploitability));
+        assert!((report.results[0].exploitability - exploitability).abs() < 1e-3);
+        assert_eq!(report.num_nodes, 10);
+    }
+
+    #[test]
+    fn simplifying_a_mixed_strategy_costs() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        // below a check: the IP decision, the OOP responses to both bets and IP facing all-in
+        let report = analyze_at(&mut game, &[0], &[SimplificationForm::Pure]);
+        assert_eq!(report.num_nodes, 5);
+        assert!(report.results[0].cost > 0.0, "{}", report.results[0].cost);
+        assert!(game.history().is_empty());
+    }
+}
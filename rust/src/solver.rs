@@ -1,29 +1,13 @@
+use crate::cards::{HistoryValue, cards_to_mask};
+use crate::config::GameConfig;
 use crate::range::*;
+use crate::state::MemoryBudget;
+use crate::tree::{NodeType, Street, encode_action, try_decode_action, walk_tree};
 
 use postflop_solver::*;
 use rayon::ThreadPool;
 use serde::Serialize;
 
-#[inline]
-fn decode_action(action: &str) -> Action {
-    match action {
-        "F" => Action::Fold,
-        "X" => Action::Check,
-        "C" => Action::Call,
-        _ => {
-            let mut chars = action.chars();
-            let first_char = chars.next().unwrap();
-            let amount = chars.as_str().parse().unwrap();
-            match first_char {
-                'B' => Action::Bet(amount),
-                'R' => Action::Raise(amount),
-                'A' => Action::AllIn(amount),
-                _ => unreachable!(),
-            }
-        }
-    }
-}
-
 #[inline]
 fn action_usize(action: isize) -> usize {
     match action {
@@ -33,7 +17,7 @@ fn action_usize(action: isize) -> usize {
 }
 
 #[inline]
-fn round(value: f64) -> f64 {
+pub fn round(value: f64) -> f64 {
     if value < 1.0 {
         (value * 1000000.0).round() / 1000000.0
     } else if value < 10.0 {
@@ -50,7 +34,7 @@ fn round(value: f64) -> f64 {
 }
 
 #[inline]
-fn round_iter<'a>(iter: impl Iterator<Item = &'a f32> + 'a) -> impl Iterator<Item = f64> + 'a {
+pub fn round_iter<'a>(iter: impl Iterator<Item = &'a f32> + 'a) -> impl Iterator<Item = f64> + 'a {
     iter.map(|&x| round(x as f64))
 }
 
@@ -65,109 +49,135 @@ pub fn weighted_average(slice: &[f32], weights: &[f32]) -> f64 {
     sum / weight_sum
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn game_init(
     range_state: &RangeManager,
     game_state: &mut PostFlopGame,
-    board: Vec<u8>,
-    starting_pot: i32,
-    effective_stack: i32,
-    rake_rate: f64,
-    rake_cap: f64,
-    donk_option: bool,
-    oop_flop_bet: String,
-    oop_flop_raise: String,
-    oop_turn_bet: String,
-    oop_turn_raise: String,
-    oop_turn_donk: String,
-    oop_river_bet: String,
-    oop_river_raise: String,
-    oop_river_donk: String,
-    ip_flop_bet: String,
-    ip_flop_raise: String,
-    ip_turn_bet: String,
-    ip_turn_raise: String,
-    ip_river_bet: String,
-    ip_river_raise: String,
-    add_allin_threshold: f64,
-    force_allin_threshold: f64,
-    merging_threshold: f64,
-    added_lines: String,
-    removed_lines: String,
+    config: &GameConfig,
 ) -> Option<String> {
-    let (turn, river, state) = match board.len() {
-        3 => (NOT_DEALT, NOT_DEALT, BoardState::Flop),
-        4 => (board[3], NOT_DEALT, BoardState::Turn),
-        5 => (board[3], board[4], BoardState::River),
-        _ => return Some("Invalid board length".to_string()),
-    };
-
     let ranges = &range_state.0;
-    let card_config = CardConfig {
-        range: ranges[..2].try_into().unwrap(),
-        flop: board[..3].try_into().unwrap(),
-        turn,
-        river,
-    };
+    let result = config
+        .card_config(ranges[..2].try_into().unwrap())
+        .and_then(|card_config| Ok((card_config, config.game_tree()?)));
 
-    let tree_config = TreeConfig {
-        initial_state: state,
-        starting_pot,
-        effective_stack,
-        rake_rate,
-        rake_cap,
-        flop_bet_sizes: [
-            BetSizeOptions::try_from((oop_flop_bet.as_str(), oop_flop_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_flop_bet.as_str(), ip_flop_raise.as_str())).unwrap(),
-        ],
-        turn_bet_sizes: [
-            BetSizeOptions::try_from((oop_turn_bet.as_str(), oop_turn_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_turn_bet.as_str(), ip_turn_raise.as_str())).unwrap(),
-        ],
-        river_bet_sizes: [
-            BetSizeOptions::try_from((oop_river_bet.as_str(), oop_river_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_river_bet.as_str(), ip_river_raise.as_str())).unwrap(),
-        ],
-        turn_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_turn_donk.as_str()).ok(),
-        },
-        river_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_river_donk.as_str()).ok(),
-        },
-        add_allin_threshold,
-        force_allin_threshold,
-        merging_threshold,
-    };
+    match result {
+        Ok((card_config, action_tree)) => game_state.update_config(card_config, action_tree).err(),
+        Err(e) => Some(e),
+    }
+}
 
-    let mut action_tree = ActionTree::new(tree_config).unwrap();
+#[derive(Serialize)]
+pub struct MemoryEstimate {
+    uncompressed: u64,
+    compressed: u64,
+    bunching: u64,
+    available_memory: u64,
+    total_memory: u64,
+    max_memory: u64,
+    fits_uncompressed: bool,
+    fits_compressed: bool,
+    fits_uncompressed_bunching: bool,
+    fits_compressed_bunching: bool,
+}
+
+/// Rough size of a node of the game tree besides its storage.
+const NODE_SIZE: u64 = 40;
+
+/// Computes the memory usage from the action tree and the number of hands of each player, without
+/// building the game. Decision nodes store the strategy and the regrets of the player to act, and
+/// chance nodes the counterfactual values of both players. Runouts are counted without suit
+/// isomorphism, so the figures are an upper bound when the board and ranges have symmetric suits.
+fn estimate_memory_usage(
+    action_tree: &mut ActionTree,
+    card_config: &CardConfig,
+    config: &GameConfig,
+) -> (u64, u64, u64) {
+    let board = cards_to_mask(&config.board);
+    let num_hands = card_config.range.map(|range| {
+        let raw_data = range.raw_data();
+        (0..raw_data.len())
+            .filter(|&index| {
+                let (c1, c2) = index_to_card_pair(index);
+                raw_data[index] > 0.0 && board & (1 << c1 | 1 << c2) == 0
+            })
+            .count() as u64
+    });
+
+    // each chance node multiplies the number of nodes below it by the cards left in the deck
+    let deck = 52 - board.count_ones() as u64;
+    let initial_street = Street::from_board_state(action_tree.config().initial_state);
+    let runouts = |street: Street| {
+        let dealt = street as u64 - initial_street as u64;
+        (0..dealt).map(|i| deck - i).product::<u64>()
+    };
 
-    if !added_lines.is_empty() {
-        for added_line in added_lines.split(',') {
-            let line = added_line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if action_tree.add_line(&line).is_err() {
-                return Some("Failed to add line (loaded broken tree?)".to_string());
+    let (mut num_nodes, mut num_elements, mut num_arrays) = (0, 0, 0);
+    walk_tree(action_tree, &mut |tree, node| {
+        let runouts = runouts(node.street);
+        num_nodes += runouts;
+        match node.node_type {
+            NodeType::Decision => {
+                let num_actions = tree.available_actions().len() as u64;
+                num_elements += runouts * 2 * num_actions * num_hands[node.player];
+                num_arrays += runouts * 2;
             }
+            NodeType::Chance => {
+                num_elements += runouts * (num_hands[0] + num_hands[1]);
+                num_arrays += runouts * 2;
+            }
+            NodeType::Terminal => {}
         }
-    }
+    });
+
+    // compressed storage keeps 16-bit values and a 32-bit scale per array
+    let uncompressed = NODE_SIZE * num_nodes + 4 * num_elements;
+    let compressed = NODE_SIZE * num_nodes + 2 * num_elements + 4 * num_arrays;
+    // bunching keeps the folded-range weights of each hand for every river board
+    let bunching = 4 * (num_hands[0] + num_hands[1]) * runouts(Street::River);
+    (uncompressed, compressed, bunching)
+}
 
-    if !removed_lines.is_empty() {
-        for removed_line in removed_lines.split(',') {
-            let line = removed_line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if action_tree.remove_line(&line).is_err() {
-                return Some("Failed to remove line (loaded broken tree?)".to_string());
-            }
+/// Estimates the memory usage of a game without building it. `ranges` overrides the
+/// OOP/IP ranges of the range manager.
+pub fn game_estimate_memory(
+    range_state: &RangeManager,
+    ranges: [Option<String>; 2],
+    (available_memory, total_memory): (u64, u64),
+    config: &GameConfig,
+) -> Result<MemoryEstimate, String> {
+    let mut game_ranges: [Range; 2] = range_state.0[..2].try_into().unwrap();
+    for (range, str) in game_ranges.iter_mut().zip(ranges) {
+        if let Some(str) = str {
+            *range = Range::from_sanitized_str(&str)?;
         }
     }
 
-    game_state.update_config(card_config, action_tree).err()
+    let card_config = config.card_config(game_ranges)?;
+    let mut action_tree = config.game_tree()?;
+    let (uncompressed, compressed, bunching) =
+        estimate_memory_usage(&mut action_tree, &card_config, config);
+
+    // same limit as the client: available memory is not useful on macOS
+    let max_memory = match cfg!(target_os = "macos") {
+        true => (total_memory as f64 * 0.7) as u64,
+        false => available_memory,
+    };
+
+    Ok(MemoryEstimate {
+        uncompressed,
+        compressed,
+        bunching,
+        available_memory,
+        total_memory,
+        max_memory,
+        fits_uncompressed: uncompressed <= max_memory,
+        fits_compressed: compressed <= max_memory,
+        fits_uncompressed_bunching: uncompressed + bunching <= max_memory,
+        fits_compressed_bunching: compressed + bunching <= max_memory,
+    })
+}
+
+pub fn game_private_cards_notation(game_state: &PostFlopGame) -> [Vec<String>; 2] {
+    [0, 1].map(|player| holes_to_strings(game_state.private_cards(player)).unwrap())
 }
 
 pub fn game_private_cards(game_state: &PostFlopGame) -> [Vec<u16>; 2] {
@@ -189,20 +199,66 @@ pub fn game_memory_usage_bunching(game_state: &PostFlopGame) -> u64 {
     game_state.memory_usage_bunching()
 }
 
-pub fn game_allocate_memory(game_state: &mut PostFlopGame, enable_compression: bool) {
+pub fn game_allocate_memory(
+    game_state: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    enable_compression: bool,
+) -> Option<String> {
+    let (uncompressed, compressed) = game_state.memory_usage();
+    let required = match enable_compression {
+        false => uncompressed,
+        true => compressed,
+    };
+    if let Err(e) = memory_budget.reserve(reserved_memory, required) {
+        return Some(e);
+    }
     game_state.allocate_memory(enable_compression);
+    None
 }
 
 pub fn game_set_bunching(
     bunching_state: &Option<BunchingData>,
     game_state: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
 ) -> Option<String> {
-    let bunching_data = bunching_state.as_ref().unwrap();
-    game_state.set_bunching_effect(bunching_data).err()
+    let Some(bunching_data) = bunching_state.as_ref() else {
+        return Some("Bunching data is not loaded".to_string());
+    };
+
+    // the reservation covers the allocated storage plus the bunching tables, however many times
+    // this is called
+    let (uncompressed, compressed) = game_state.memory_usage();
+    let allocated = match game_state.is_compression_enabled() {
+        false => uncompressed,
+        true => compressed,
+    };
+    let previous = *reserved_memory;
+    let required = allocated + game_state.memory_usage_bunching();
+    if let Err(e) = memory_budget.reserve(reserved_memory, required) {
+        return Some(e);
+    }
+
+    let result = game_state.set_bunching_effect(bunching_data);
+    if result.is_err() {
+        // going back to an accepted reservation cannot exceed a cap
+        let _ = memory_budget.reserve(reserved_memory, previous);
+    }
+    result.err()
 }
 
-pub fn game_solve_step(game_state: &PostFlopGame, pool: &ThreadPool, current_iteration: u32) {
+pub fn game_solve_step(
+    game_state: &PostFlopGame,
+    pool: &ThreadPool,
+    current_iteration: u32,
+) -> Option<String> {
+    // refused allocations leave the game unallocated; solving it would abort the process
+    if !game_state.is_ready() {
+        return Some("Game is not ready".to_string());
+    }
     pool.install(|| solve_step(game_state, current_iteration));
+    None
 }
 
 pub fn game_solve_steps_with_exploitability(
@@ -211,6 +267,9 @@ pub fn game_solve_steps_with_exploitability(
     current_iteration: u32,
     num_iterations: u32,
 ) -> f32 {
+    if !game_state.is_ready() {
+        return f32::INFINITY;
+    }
     pool.install(|| {
         for cur in current_iteration..(current_iteration + num_iterations) {
             solve_step(game_state, cur);
@@ -220,6 +279,9 @@ pub fn game_solve_steps_with_exploitability(
 }
 
 pub fn game_exploitability(game_state: &PostFlopGame, pool: &ThreadPool) -> f32 {
+    if !game_state.is_ready() && !game_state.is_solved() {
+        return f32::INFINITY;
+    }
     pool.install(|| compute_exploitability(game_state))
 }
 
@@ -227,10 +289,130 @@ pub fn game_finalize(game_state: &mut PostFlopGame, pool: &ThreadPool) {
     pool.install(|| finalize(game_state));
 }
 
+/// Builds an unsolved copy of `game` with the same cards and tree, and its memory allocated.
+fn copy_game(game: &PostFlopGame) -> Result<PostFlopGame, String> {
+    let mut action_tree = ActionTree::new(game.tree_config().clone())?;
+    for line in game.added_lines() {
+        action_tree.add_line(line)?;
+    }
+    for line in game.removed_lines() {
+        action_tree.remove_line(line)?;
+    }
+    let mut copy = PostFlopGame::with_config(game.card_config().clone(), action_tree)?;
+    copy.allocate_memory(game.is_compression_enabled());
+    Ok(copy)
+}
+
+/// Locks the decision nodes of `copy` below its current node to the strategies of `game` at the
+/// same nodes, or to `replace` for the nodes below `node` when it returns a strategy. Returns the
+/// number of decision nodes below `node`.
+fn lock_strategies(
+    game: &mut PostFlopGame,
+    copy: &mut PostFlopGame,
+    node: &[usize],
+    replace: &mut dyn FnMut(&PostFlopGame) -> Option<Vec<f32>>,
+) -> usize {
+    if game.is_terminal_node() {
+        return 0;
+    }
+
+    let history = game.history().to_vec();
+    let (mut count, children) = match game.is_chance_node() {
+        true => {
+            let possible_cards = game.possible_cards();
+            let cards = (0..52).filter(|&card| possible_cards & (1 << card) != 0);
+            (0, cards.collect())
+        }
+        false => {
+            let below = history.starts_with(node);
+            let strategy = below.then(|| replace(game)).flatten();
+            copy.lock_current_strategy(&strategy.unwrap_or_else(|| game.strategy()));
+            (
+                below as usize,
+                (0..game.available_actions().len()).collect::<Vec<_>>(),
+            )
+        }
+    };
+    for child in children {
+        game.play(child);
+        copy.play(child);
+        count += lock_strategies(game, copy, node, replace);
+        game.apply_history(&history);
+        copy.apply_history(&history);
+    }
+    count
+}
+
+/// Runs `f` on a copy of the solved `game` in which every strategy is locked to the solution,
+/// except that `replace` may give the strategy of the nodes below the current node. The copy is
+/// finalized, so the engine's exploitability and EVs of the locked strategies can be read from it;
+/// `f` also gets the number of decision nodes below the current node. The memory of the copy is
+/// reserved for the session while it exists.
+pub fn with_locked_copy<T: Send>(
+    game: &mut PostFlopGame,
+    memory_budget: &MemoryBudget,
+    reserved_memory: &mut u64,
+    pool: &ThreadPool,
+    replace: &mut dyn FnMut(&PostFlopGame) -> Option<Vec<f32>>,
+    f: impl FnOnce(&PostFlopGame, usize) -> T + Send,
+) -> Result<T, String> {
+    let (uncompressed, compressed) = game.memory_usage();
+    let required = match game.is_compression_enabled() {
+        false => uncompressed,
+        true => compressed,
+    };
+    let previous = *reserved_memory;
+    memory_budget.reserve(reserved_memory, previous + required)?;
+
+    let result = copy_game(game).map(|mut copy| {
+        let node = game.history().to_vec();
+        game.back_to_root();
+        let num_nodes = lock_strategies(game, &mut copy, &node, replace);
+        game.apply_history(&node);
+        copy.back_to_root();
+
+        // with every node locked, a single iteration sets the strategies
+        pool.install(|| {
+            solve_step(&copy, 0);
+            finalize(&mut copy);
+            f(&copy, num_nodes)
+        })
+    });
+
+    // going back to an accepted reservation cannot exceed a cap
+    let _ = memory_budget.reserve(reserved_memory, previous);
+    result
+}
+
 pub fn game_apply_history(game_state: &mut PostFlopGame, history: Vec<usize>) {
     game_state.apply_history(&history);
 }
 
+/// Moves to the node reached by `history` from the root, checking every index first since
+/// `apply_history` panics on an action or card that does not exist. On error the current node is
+/// left unchanged.
+pub fn try_apply_history(game: &mut PostFlopGame, history: &[usize]) -> Result<(), String> {
+    let saved = game.history().to_vec();
+    game.back_to_root();
+    for (i, &index) in history.iter().enumerate() {
+        let error = if game.is_terminal_node() {
+            Some(format!("History continues after a terminal node (#{i})"))
+        } else if game.is_chance_node() {
+            let possible = index < 52 && game.possible_cards() & (1 << index) != 0;
+            (!possible).then(|| format!("Card cannot be dealt: {index} (#{i})"))
+        } else {
+            let available = index < game.available_actions().len();
+            (!available).then(|| format!("Action not available: {index} (#{i})"))
+        };
+        if let Some(error) = error {
+            game.apply_history(&saved);
+            return Err(error);
+        }
+        game.play(index);
+    }
+    Ok(())
+}
+
 pub fn game_total_bet_amount(game_state: &mut PostFlopGame, append: Vec<isize>) -> [i32; 2] {
     if append.is_empty() {
         return game_state.total_bet_amount();
@@ -278,10 +460,164 @@ pub fn game_actions_after(game_state: &mut PostFlopGame, append: Vec<isize>) ->
     ret
 }
 
+/// Converts a line such as `X-B30-C|Td|B60` into the history accepted by `game_apply_history`.
+/// Chance nodes must be given an explicit card.
+pub fn game_line_to_history(
+    game_state: &mut PostFlopGame,
+    line: &str,
+) -> Result<Vec<usize>, String> {
+    if !game_state.is_ready() && !game_state.is_solved() {
+        return Err("Game is not ready".to_string());
+    }
+
+    let saved = game_state.history().to_vec();
+    game_state.back_to_root();
+    let result = line_to_history(game_state, line);
+    game_state.apply_history(&saved);
+    result
+}
+
+fn line_to_history(game: &mut PostFlopGame, line: &str) -> Result<Vec<usize>, String> {
+    let mut history = Vec::new();
+    if line.is_empty() || line == "(Root)" {
+        return Ok(history);
+    }
+
+    for (i, token) in line.split(&['-', '|'][..]).enumerate() {
+        if game.is_terminal_node() {
+            return Err(format!(
+                "Line continues after a terminal node: {token} (#{i})"
+            ));
+        }
+
+        let index = if game.is_chance_node() {
+            let card = card_from_str(token)
+                .map_err(|_| format!("Expected a card at chance node: {token} (#{i})"))?;
+            if game.possible_cards() & (1 << card) == 0 {
+                return Err(format!("Card cannot be dealt: {token} (#{i})"));
+            }
+            card as usize
+        } else {
+            let action = try_decode_action(token)
+                .ok_or_else(|| format!("Invalid action: {token} (#{i})"))?;
+            game.available_actions()
+                .iter()
+                .position(|&a| a == action)
+                .ok_or_else(|| format!("Action not available: {token} (#{i})"))?
+        };
+
+        game.play(index);
+        history.push(index);
+    }
+
+    Ok(history)
+}
+
+/// Writes the chance cards of `history` in notation, e.g. `[0, 1, "Td", 2]`.
+pub fn history_to_notation(game: &mut PostFlopGame, history: &[usize]) -> Vec<HistoryValue<usize>> {
+    let saved = game.history().to_vec();
+    game.back_to_root();
+    let result = history
+        .iter()
+        .map(|&index| {
+            let value = match game.is_chance_node() {
+                true => HistoryValue::Card(card_to_string(index as Card).unwrap()),
+                false => HistoryValue::Index(index),
+            };
+            game.play(index);
+            value
+        })
+        .collect();
+    game.apply_history(&saved);
+    result
+}
+
+/// Inverse of `game_line_to_history`. `-1` at a chance node deals the first possible card.
+pub fn game_history_to_line(
+    game_state: &mut PostFlopGame,
+    history: Vec<isize>,
+) -> Result<String, String> {
+    if !game_state.is_ready() && !game_state.is_solved() {
+        return Err("Game is not ready".to_string());
+    }
+    history_to_line(game_state, &history)
+}
+
+/// Same as `game_history_to_line` for callers that already checked the state of the game. The
+/// current node is left unchanged.
+pub fn history_to_line(game: &mut PostFlopGame, history: &[isize]) -> Result<String, String> {
+    let saved = game.history().to_vec();
+    game.back_to_root();
+    let result = encode_history(game, history);
+    game.apply_history(&saved);
+    result
+}
+
+fn encode_history(game: &mut PostFlopGame, history: &[isize]) -> Result<String, String> {
+    // same delimiters as `encode_line`, with `|` around dealt cards
+    let mut flag = 0;
+    let mut line = String::new();
+
+    for (i, &action) in history.iter().enumerate() {
+        if game.is_terminal_node() {
+            return Err(format!("History continues after a terminal node (#{i})"));
+        }
+
+        let is_chance = game.is_chance_node();
+        if !line.is_empty() {
+            line.push(if is_chance || flag == 2 { '|' } else { '-' });
+        }
+
+        let index = if is_chance {
+            let possible_cards = game.possible_cards();
+            let card = match action {
+                -1 => possible_cards.trailing_zeros() as usize,
+                a => a as usize,
+            };
+            if card >= 52 || possible_cards & (1 << card) == 0 {
+                return Err(format!("Card cannot be dealt: {action} (#{i})"));
+            }
+            line.push_str(&card_to_string(card as Card).unwrap());
+            flag = 2;
+            card
+        } else {
+            let actions = game.available_actions();
+            let index = usize::try_from(action).ok().filter(|&a| a < actions.len());
+            let Some(index) = index else {
+                return Err(format!("Action not available: {action} (#{i})"));
+            };
+            flag = if flag == 2 { 0 } else { flag };
+            match actions[index] {
+                Action::Check => flag += 1,
+                Action::Call => flag = 2,
+                _ => flag = 0,
+            }
+            line.push_str(&encode_action(actions[index]));
+            index
+        };
+
+        game.play(index);
+    }
+
+    match line.is_empty() {
+        true => Ok("(Root)".to_string()),
+        false => Ok(line),
+    }
+}
+
 pub fn game_possible_cards(game_state: &PostFlopGame) -> u64 {
     game_state.possible_cards()
 }
 
+pub fn game_range_export(game_state: &PostFlopGame, player: usize, format: RangeFormat) -> String {
+    let mut weights = vec![0.0; 1326];
+    let private_cards = game_state.private_cards(player);
+    for (&(c1, c2), &w) in private_cards.iter().zip(game_state.weights(player)) {
+        weights[card_pair_to_index(c1, c2)] = w;
+    }
+    format_weights(&weights, format)
+}
+
 fn current_player(game: &PostFlopGame) -> String {
     if game.is_terminal_node() {
         "terminal".to_string()
@@ -405,12 +741,16 @@ pub struct GameChanceReportsResponse {
     ev: [Vec<f64>; 2],
     eqr: [Vec<f64>; 2],
     strategy: Vec<f64>,
+    /// Card of each index of the arrays above, when notation is requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cards: Option<Vec<String>>,
 }
 
 pub fn game_get_chance_reports(
     game_state: &mut PostFlopGame,
     append: Vec<isize>,
     num_actions: usize,
+    notation: bool,
 ) -> GameChanceReportsResponse {
     let history = game_state.history().to_vec();
 
@@ -499,5 +839,87 @@ pub fn game_get_chance_reports(
         ev,
         eqr,
         strategy,
+        cards: notation.then(|| (0..52).map(|card| card_to_string(card).unwrap()).collect()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use serde_json::json;
+
+    /// Builds a game with memory allocated on `board`, with a starting pot of 100 and a stack of
+    /// 900. Only the river has bets: half and full pot, with all-in raises.
+    pub(crate) fn build_game(board: &str, ranges: [&str; 2]) -> PostFlopGame {
+        let sizes = json!({
+            "flop": { "bet": "", "raise": "" },
+            "turn": { "bet": "", "raise": "" },
+            "river": { "bet": "50%, 100%", "raise": "a" },
+        });
+        let config = parse_config(json!({
+            "version": 2,
+            "board": board,
+            "startingPot": 100,
+            "effectiveStack": 900,
+            "sizes": [sizes, sizes],
+            "donk": { "turnEnabled": false, "turn": "", "riverEnabled": false, "river": "" },
+            "addAllinThreshold": 0.0,
+            "forceAllinThreshold": 0.0,
+            "mergingThreshold": 0.0,
+        }))
+        .unwrap();
+        let ranges = ranges.map(|r| Range::from_sanitized_str(r).unwrap());
+        let card_config = config.card_config(ranges).unwrap();
+        let mut game = PostFlopGame::with_config(card_config, config.game_tree().unwrap()).unwrap();
+        game.allocate_memory(false);
+        game
+    }
+
+    /// Solves the river spot on Ts7h2cKc5d of [`build_game`].
+    pub(crate) fn solved_river_game(ranges: [&str; 2]) -> PostFlopGame {
+        let mut game = build_game("Ts7h2cKc5d", ranges);
+        solve(&mut game, 1000, 0.1, false);
+        game
+    }
+
+    #[test]
+    fn memory_estimate_bounds_the_built_game() {
+        let sizes = json!({
+            "flop": { "bet": "50%", "raise": "3x" },
+            "turn": { "bet": "50%", "raise": "3x" },
+            "river": { "bet": "50%", "raise": "a" },
+        });
+        // every suit is on the board, so the engine finds no isomorphic runouts either
+        let config = parse_config(json!({
+            "version": 2,
+            "board": "QsJh2d8c",
+            "startingPot": 100,
+            "effectiveStack": 200,
+            "sizes": [sizes, sizes],
+            "donk": { "turnEnabled": false, "turn": "", "riverEnabled": false, "river": "" },
+            "addAllinThreshold": 1.5,
+            "forceAllinThreshold": 0.15,
+            "mergingThreshold": 0.1,
+        }))
+        .unwrap();
+        let ranges = ["AA,KK,AKs", "QQ-TT,KQs"].map(|r| Range::from_sanitized_str(r).unwrap());
+        let card_config = config.card_config(ranges).unwrap();
+
+        let mut action_tree = config.game_tree().unwrap();
+        let (uncompressed, compressed, bunching) =
+            estimate_memory_usage(&mut action_tree, &card_config, &config);
+        let game = PostFlopGame::with_config(card_config, config.game_tree().unwrap()).unwrap();
+        let (actual_uncompressed, actual_compressed) = game.memory_usage();
+        let actual_bunching = game.memory_usage_bunching();
+
+        for (estimate, actual) in [
+            (uncompressed, actual_uncompressed),
+            (compressed, actual_compressed),
+            (bunching, actual_bunching),
+        ] {
+            assert!(actual <= estimate, "{actual} > {estimate}");
+            assert!(estimate <= 2 * actual, "{estimate} > 2 * {actual}");
+        }
     }
 }
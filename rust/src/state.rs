@@ -1,36 +1,108 @@
+use crate::library::Library;
 use crate::range::RangeManager;
-use crate::tree::default_action_tree;
+use crate::trainer::TrainerState;
+use crate::tree::{TreeJournal, TreeOrigin, default_action_tree};
 
 use parking_lot::Mutex;
 use postflop_solver::{ActionTree, BunchingData, PostFlopGame};
 use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::sync::Arc;
+
+/// Memory caps in bytes shared by all sessions. `None` means unlimited.
+pub struct MemoryBudget {
+    total_limit: Option<u64>,
+    session_limit: Option<u64>,
+    used: Mutex<u64>,
+}
+
+impl MemoryBudget {
+    pub fn new(total_limit: Option<u64>, session_limit: Option<u64>) -> Self {
+        Self {
+            total_limit,
+            session_limit,
+            used: Mutex::new(0),
+        }
+    }
+
+    /// Changes the reservation of a session from `*reserved` to `amount` bytes, or returns an
+    /// error without changing anything if that would exceed a cap.
+    pub fn reserve(&self, reserved: &mut u64, amount: u64) -> Result<(), String> {
+        let to_mib = |x: u64| x.div_ceil(1 << 20);
+        let mut used = self.used.lock();
+        let new_used = *used - *reserved + amount;
+
+        if let Some(limit) = self.session_limit.filter(|&limit| amount > limit) {
+            return Err(format!(
+                "Memory limit exceeded: {}MB required, {}MB allowed per session",
+                to_mib(amount),
+                to_mib(limit),
+            ));
+        }
+
+        if let Some(limit) = self.total_limit.filter(|&limit| new_used > limit) {
+            return Err(format!(
+                "Memory limit exceeded: {}MB required, {}MB of {}MB available on this server",
+                to_mib(amount),
+                to_mib(limit.saturating_sub(*used - *reserved)),
+                to_mib(limit),
+            ));
+        }
+
+        *used = new_used;
+        *reserved = amount;
+        Ok(())
+    }
+
+    pub fn release(&self, reserved: &mut u64) {
+        *self.used.lock() -= *reserved;
+        *reserved = 0;
+    }
+}
 
 pub struct SessionState {
     pub range_manager: Mutex<RangeManager>,
     pub action_tree: Mutex<ActionTree>,
+    pub tree_journal: Mutex<TreeJournal>,
+    pub tree_origin: Mutex<TreeOrigin>,
     pub bunching_data: Mutex<Option<BunchingData>>,
     pub post_flop_game: Mutex<PostFlopGame>,
+    /// Incremented whenever `post_flop_game` is replaced.
+    pub game_generation: Mutex<u64>,
+    pub trainer: Mutex<TrainerState>,
     pub thread_pool: Mutex<ThreadPool>,
+    pub library: Mutex<Library>,
+    pub memory_budget: Arc<MemoryBudget>,
+    pub reserved_memory: Mutex<u64>,
 }
 
-impl Default for SessionState {
-    fn default() -> Self {
+impl SessionState {
+    pub fn new(library: Library, memory_budget: Arc<MemoryBudget>) -> Self {
         Self {
             range_manager: Mutex::new(Default::default()),
             action_tree: Mutex::new(default_action_tree()),
+            tree_journal: Mutex::new(Default::default()),
+            tree_origin: Mutex::new(Default::default()),
             bunching_data: Mutex::new(None),
             post_flop_game: Mutex::new(Default::default()),
+            game_generation: Mutex::new(0),
+            trainer: Mutex::new(Default::default()),
             thread_pool: Mutex::new(ThreadPoolBuilder::new().build().unwrap()),
+            library: Mutex::new(library),
+            memory_budget,
+            reserved_memory: Mutex::new(0),
         }
     }
-}
 
-impl SessionState {
     pub fn reset(&self) {
         *self.range_manager.lock() = Default::default();
         *self.action_tree.lock() = default_action_tree();
+        self.tree_journal.lock().clear();
+        *self.tree_origin.lock() = Default::default();
         *self.bunching_data.lock() = None;
         *self.post_flop_game.lock() = Default::default();
+        *self.game_generation.lock() += 1;
+        *self.trainer.lock() = Default::default();
+        self.memory_budget.release(&mut self.reserved_memory.lock());
         *self.thread_pool.lock() = ThreadPoolBuilder::new().build().unwrap();
     }
 }
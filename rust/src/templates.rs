@@ -0,0 +1,83 @@
+use crate::config::{DonkSizes, GameConfig, StreetSizes};
+
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// Named bet-size configuration. Applying it replaces the sizes, donk settings and thresholds of
+/// a configuration and keeps the board, pot, stack and rake.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeTemplate {
+    id: String,
+    name: String,
+    description: String,
+    sizes: [StreetSizes; 2],
+    donk: DonkSizes,
+    add_allin_threshold: f64,
+    force_allin_threshold: f64,
+    merging_threshold: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TemplateLibrary {
+    version: u32,
+    templates: Vec<TreeTemplate>,
+}
+
+static TEMPLATES: LazyLock<TemplateLibrary> =
+    LazyLock::new(|| serde_json::from_str(include_str!("../templates/trees.json")).unwrap());
+
+pub fn template_list() -> &'static TemplateLibrary {
+    &TEMPLATES
+}
+
+/// Edited lines are dropped since they are unlikely to exist in the new tree.
+pub fn template_apply(mut config: GameConfig, id: String) -> Result<GameConfig, String> {
+    let Some(template) = TEMPLATES.templates.iter().find(|t| t.id == id) else {
+        return Err(format!("Template not found: {id}"));
+    };
+
+    config.sizes = template.sizes.clone();
+    config.donk = template.donk.clone();
+    config.add_allin_threshold = template.add_allin_threshold;
+    config.force_allin_threshold = template.force_allin_threshold;
+    config.merging_threshold = template.merging_threshold;
+    config.added_lines.clear();
+    config.removed_lines.clear();
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postflop_solver::{BetSizeOptions, DonkSizeOptions};
+    use std::collections::HashSet;
+
+    #[test]
+    fn bundled_templates_parse() {
+        let templates = &template_list().templates;
+        assert!(!templates.is_empty());
+
+        let mut ids = HashSet::new();
+        for template in templates {
+            assert!(ids.insert(&template.id), "duplicate id: {}", template.id);
+            for sizes in &template.sizes {
+                for street in [&sizes.flop, &sizes.turn, &sizes.river] {
+                    let options =
+                        BetSizeOptions::try_from((street.bet.as_str(), street.raise.as_str()));
+                    assert!(options.is_ok(), "{}: {:?}", template.id, options.err());
+                }
+            }
+            let donk = &template.donk;
+            for (enabled, sizes) in [
+                (donk.turn_enabled, &donk.turn),
+                (donk.river_enabled, &donk.river),
+            ] {
+                if enabled {
+                    let options = DonkSizeOptions::try_from(sizes.as_str());
+                    assert!(options.is_ok(), "{}: {:?}", template.id, options.err());
+                }
+            }
+        }
+    }
+}
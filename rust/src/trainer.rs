@@ -0,0 +1,471 @@
+use crate::cards::cards_to_string;
+use crate::sampling::{Rng, resolve_seed, sample_action};
+use crate::solver::{history_to_line, round, try_apply_history};
+use crate::tree::encode_action;
+
+use postflop_solver::*;
+use serde::Serialize;
+
+/// Answers losing at most this fraction of the pot are graded as correct by default.
+const DEFAULT_TOLERANCE: f64 = 0.01;
+
+#[derive(Clone)]
+struct TrainerSession {
+    history: Vec<usize>,
+    hero: usize,
+    hands: [usize; 2],
+    /// Hole cards of both players, never dealt at chance nodes.
+    blocked: u64,
+    tolerance: f64,
+    rng: Rng,
+}
+
+#[derive(Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainerStats {
+    hands: u32,
+    decisions: u32,
+    correct: u32,
+    total_ev_loss: f64,
+}
+
+#[derive(Default)]
+pub struct TrainerState {
+    session: Option<TrainerSession>,
+    stats: TrainerStats,
+}
+
+impl TrainerState {
+    /// Drops the hand in progress, e.g. when the game is replaced. Stats are kept.
+    pub fn end_session(&mut self) {
+        self.session = None;
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainerQuestion {
+    line: String,
+    board: String,
+    pot: f64,
+    actions: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainerGrade {
+    action: String,
+    best_action: String,
+    ev_loss: f64,
+    correct: bool,
+    actions: Vec<String>,
+    strategy: Vec<f64>,
+    action_ev: Vec<f64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrainerResponse {
+    seed: Option<u64>,
+    hero: usize,
+    hero_hand: String,
+    /// Revealed once the hand is over.
+    villain_hand: Option<String>,
+    grade: Option<TrainerGrade>,
+    /// The next decision of hero, or `None` if the hand is over.
+    question: Option<TrainerQuestion>,
+    /// Why the hand ended before the end of the line, if it did.
+    message: Option<String>,
+    line: String,
+    stats: TrainerStats,
+    accuracy: f64,
+}
+
+fn hand_to_string(game: &PostFlopGame, player: usize, index: usize) -> String {
+    let (c1, c2) = game.private_cards(player)[index];
+    cards_to_string(&[c1, c2])
+}
+
+fn hand_mask(game: &PostFlopGame, player: usize, index: usize) -> u64 {
+    let (c1, c2) = game.private_cards(player)[index];
+    1 << c1 | 1 << c2
+}
+
+fn current_pot(game: &PostFlopGame) -> f64 {
+    let total = game.total_bet_amount();
+    (game.tree_config().starting_pot + total[0] + total[1]) as f64
+}
+
+/// Picks a combo of `player` with probability proportional to its reach at the current node.
+fn pick_combo(game: &PostFlopGame, player: usize, blocked: u64, rng: &mut Rng) -> Option<usize> {
+    let weights = game.weights(player);
+    let available = |i: usize| hand_mask(game, player, i) & blocked == 0;
+    let sum = (0..weights.len())
+        .filter(|&i| available(i))
+        .map(|i| weights[i] as f64)
+        .sum::<f64>();
+    if sum <= 0.0 {
+        return None;
+    }
+
+    let mut draw = rng.next_f64() * sum;
+    let mut chosen = None;
+    for i in (0..weights.len()).filter(|&i| available(i) && weights[i] > 0.0) {
+        chosen = Some(i);
+        draw -= weights[i] as f64;
+        if draw < 0.0 {
+            break;
+        }
+    }
+    chosen
+}
+
+/// Plays villain's actions and deals chance cards until hero has to act or the hand is over.
+fn advance(game: &mut PostFlopGame, session: &mut TrainerSession) -> Result<(), String> {
+    while !game.is_terminal_node() {
+        if game.is_chance_node() {
+            let possible_cards = game.possible_cards() & !session.blocked;
+            let cards = (0..52)
+                .filter(|&card| possible_cards & (1 << card) != 0)
+                .collect::<Vec<_>>();
+            if cards.is_empty() {
+                return Err("No card can be dealt".to_string());
+            }
+            let card = cards[(session.rng.next_u64() % cards.len() as u64) as usize];
+            game.play(card);
+        } else if game.current_player() == session.hero {
+            break;
+        } else {
+            let villain = session.hero ^ 1;
+            let sampled = sample_action(game, session.hands[villain], &mut session.rng)?;
+            game.play(sampled.index);
+        }
+    }
+
+    session.history = game.history().to_vec();
+    Ok(())
+}
+
+/// Builds the response for the node the session is at, then stores the session and `stats`. The
+/// session ends if the hand is over, or if hero's combo never reaches its next decision in the
+/// solved strategy since it has no EVs there; nothing is stored on error.
+fn respond(
+    game: &mut PostFlopGame,
+    state: &mut TrainerState,
+    session: TrainerSession,
+    stats: TrainerStats,
+    seed: Option<u64>,
+    grade: Option<TrainerGrade>,
+) -> Result<TrainerResponse, String> {
+    let hero = session.hero;
+    let hero_hand = hand_to_string(game, hero, session.hands[hero]);
+    let villain_hand = hand_to_string(game, hero ^ 1, session.hands[hero ^ 1]);
+    let history = session
+        .history
+        .iter()
+        .map(|&i| i as isize)
+        .collect::<Vec<_>>();
+    let line = history_to_line(game, &history)?;
+
+    let off_strategy = !game.is_terminal_node() && game.weights(hero)[session.hands[hero]] <= 0.0;
+    let question = match game.is_terminal_node() || off_strategy {
+        true => None,
+        false => Some(TrainerQuestion {
+            line: line.clone(),
+            board: cards_to_string(&game.current_board()),
+            pot: current_pot(game),
+            actions: game
+                .available_actions()
+                .into_iter()
+                .map(encode_action)
+                .collect(),
+        }),
+    };
+    state.session = question.is_some().then_some(session);
+    state.stats = stats;
+
+    Ok(TrainerResponse {
+        seed,
+        hero,
+        hero_hand,
+        villain_hand: question.is_none().then_some(villain_hand),
+        grade,
+        question,
+        message: off_strategy.then(|| {
+            "Hero's hand never takes this line in the solved strategy, so the hand ends here"
+                .to_string()
+        }),
+        line,
+        stats,
+        accuracy: match stats.decisions {
+            0 => 0.0,
+            n => round(stats.correct as f64 / n as f64),
+        },
+    })
+}
+
+fn start(
+    game: &mut PostFlopGame,
+    state: &mut TrainerState,
+    hero: Option<usize>,
+    tolerance: f64,
+    seed: u64,
+) -> Result<TrainerResponse, String> {
+    if game.is_terminal_node() || game.is_chance_node() {
+        return Err("Node is not a decision node".to_string());
+    }
+
+    let hero = hero.unwrap_or_else(|| game.current_player());
+    if hero > 1 {
+        return Err(format!("Invalid player: {hero}"));
+    }
+
+    let mut rng = Rng::new(seed);
+    let hero_index = pick_combo(game, hero, 0, &mut rng)
+        .ok_or_else(|| "No combo of hero reaches this node".to_string())?;
+    let blocked = hand_mask(game, hero, hero_index);
+    let villain_index = pick_combo(game, hero ^ 1, blocked, &mut rng)
+        .ok_or_else(|| "No combo of villain reaches this node".to_string())?;
+
+    let mut hands = [0; 2];
+    hands[hero] = hero_index;
+    hands[hero ^ 1] = villain_index;
+
+    let mut session = TrainerSession {
+        history: Vec::new(),
+        hero,
+        hands,
+        blocked: blocked | hand_mask(game, hero ^ 1, villain_index),
+        tolerance,
+        rng,
+    };
+    advance(game, &mut session)?;
+
+    let mut stats = state.stats;
+    stats.hands += 1;
+    respond(game, state, session, stats, Some(seed), None)
+}
+
+fn answer(
+    game: &mut PostFlopGame,
+    state: &mut TrainerState,
+    action: usize,
+) -> Result<TrainerResponse, String> {
+    // work on a copy so that a failed answer leaves the hand where it was
+    let mut session = state.session.clone().unwrap();
+    game.apply_history(&session.history);
+
+    let actions = game.available_actions();
+    if action >= actions.len() {
+        return Err(format!("Invalid action: {action}"));
+    }
+
+    let hero = session.hero;
+    let index = session.hands[hero];
+    if game.weights(hero)[index] <= 0.0 {
+        state.session = None;
+        return Err("Hero's hand does not reach this decision; the hand has ended".to_string());
+    }
+
+    let num_hands = game.private_cards(hero).len();
+    let strategy = game.strategy();
+    game.cache_normalized_weights();
+    let ev = game.expected_values_detail(hero);
+    let action_ev = (0..actions.len())
+        .map(|i| ev[i * num_hands + index] as f64)
+        .collect::<Vec<_>>();
+
+    let best = (0..actions.len())
+        .max_by(|&a, &b| action_ev[a].total_cmp(&action_ev[b]))
+        .unwrap();
+    let ev_loss = action_ev[best] - action_ev[action];
+    let correct = ev_loss <= session.tolerance * current_pot(game);
+
+    let grade = TrainerGrade {
+        action: encode_action(actions[action]),
+        best_action: encode_action(actions[best]),
+        ev_loss: round(ev_loss),
+        correct,
+        actions: actions.iter().cloned().map(encode_action).collect(),
+        strategy: (0..actions.len())
+            .map(|i| round(strategy[i * num_hands + index] as f64))
+            .collect(),
+        action_ev: action_ev.into_iter().map(round).collect(),
+    };
+
+    game.play(action);
+    advance(game, &mut session)?;
+
+    let mut stats = state.stats;
+    stats.decisions += 1;
+    stats.correct += correct as u32;
+    stats.total_ev_loss = round(stats.total_ev_loss + ev_loss);
+    respond(game, state, session, stats, None, Some(grade))
+}
+
+/// Starts a trainer hand at the node reached by `history`. Hero's and villain's combos are drawn
+/// according to their reach at that node, and play continues until hero has to act. `tolerance`
+/// is the EV loss, as a fraction of the pot, still graded as correct.
+pub fn trainer_start(
+    trainer_state: &mut TrainerState,
+    game_state: &mut PostFlopGame,
+    history: &[usize],
+    hero: Option<usize>,
+    tolerance: Option<f64>,
+    seed: Option<u64>,
+) -> Result<TrainerResponse, String> {
+    if !game_state.is_solved() {
+        return Err("Game is not solved".to_string());
+    }
+
+    let seed = resolve_seed(seed);
+    let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    let saved = game_state.history().to_vec();
+    try_apply_history(game_state, history)?;
+    let result = start(game_state, trainer_state, hero, tolerance, seed);
+    game_state.apply_history(&saved);
+    result
+}
+
+/// Grades hero's `action` at the current trainer decision by its EV loss, then continues the hand.
+pub fn trainer_answer(
+    trainer_state: &mut TrainerState,
+    game_state: &mut PostFlopGame,
+    action: usize,
+) -> Result<TrainerResponse, String> {
+    if trainer_state.session.is_none() {
+        return Err("No trainer hand in progress".to_string());
+    }
+
+    let saved = game_state.history().to_vec();
+    let result = answer(game_state, trainer_state, action);
+    game_state.apply_history(&saved);
+    result
+}
+
+pub fn trainer_stats(trainer_state: &TrainerState) -> TrainerStats {
+    trainer_state.stats
+}
+
+pub fn trainer_reset(trainer_state: &mut TrainerState) {
+    *trainer_state = Default::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::{build_game, solved_river_game};
+
+    const RANGES: [&str; 2] = ["AK,QQ,76s", "KK-TT,A5s,K9s"];
+
+    #[test]
+    fn combos_are_drawn_by_reach() {
+        let mut game = solved_river_game(RANGES);
+        let bet = game
+            .available_actions()
+            .iter()
+            .position(|&a| a == Action::Bet(50));
+        game.play(bet.unwrap());
+
+        // OOP's reach after betting half pot differs between combos
+        let weights = game.weights(0).to_vec();
+        let blocked = 1 << card_from_str("Ah").unwrap();
+        let available = |i: usize| hand_mask(&game, 0, i) & blocked == 0;
+        let sum = (0..weights.len())
+            .filter(|&i| available(i))
+            .map(|i| weights[i] as f64)
+            .sum::<f64>();
+
+        let mut rng = Rng::new(3);
+        let mut counts = vec![0; weights.len()];
+        let draws = 50000;
+        for _ in 0..draws {
+            counts[pick_combo(&game, 0, blocked, &mut rng).unwrap()] += 1;
+        }
+        for (i, count) in counts.into_iter().enumerate() {
+            let expected = match available(i) {
+                true => weights[i] as f64 / sum,
+                false => 0.0,
+            };
+            let frequency = count as f64 / draws as f64;
+            assert!(
+                (frequency - expected).abs() < 0.01,
+                "{i}: {frequency} vs {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn answers_are_graded_by_ev_loss_and_counted() {
+        let mut game = solved_river_game(RANGES);
+        let mut state = TrainerState::default();
+
+        let response = trainer_start(&mut state, &mut game, &[], Some(0), None, Some(5)).unwrap();
+        assert!(response.question.is_some() && response.villain_hand.is_none());
+        assert_eq!((response.stats.hands, response.stats.decisions), (1, 0));
+
+        // the hand's action EVs at the root, as the engine reports them
+        let index = state.session.as_ref().unwrap().hands[0];
+        let num_hands = game.private_cards(0).len();
+        game.cache_normalized_weights();
+        let ev = game.expected_values_detail(0);
+        let action_ev = (0..game.available_actions().len())
+            .map(|i| ev[i * num_hands + index] as f64)
+            .collect::<Vec<_>>();
+        let by_ev = |a: &usize, b: &usize| action_ev[*a].total_cmp(&action_ev[*b]);
+        let best = (0..action_ev.len()).max_by(by_ev).unwrap();
+        let worst = (0..action_ev.len()).min_by(by_ev).unwrap();
+
+        let response = trainer_answer(&mut state, &mut game, best).unwrap();
+        let grade = response.grade.unwrap();
+        assert_eq!(grade.ev_loss, 0.0);
+        assert!(grade.correct);
+        assert_eq!(grade.best_action, grade.action);
+        assert_eq!(response.stats.correct, 1);
+
+        // the same seed deals the same combos
+        trainer_start(&mut state, &mut game, &[], Some(0), None, Some(5)).unwrap();
+        assert_eq!(state.session.as_ref().unwrap().hands[0], index);
+        let response = trainer_answer(&mut state, &mut game, worst).unwrap();
+        let grade = response.grade.unwrap();
+        let loss = action_ev[best] - action_ev[worst];
+        assert_eq!(grade.ev_loss, round(loss));
+        assert_eq!(grade.correct, loss <= DEFAULT_TOLERANCE * 100.0);
+
+        let stats = trainer_stats(&state);
+        assert_eq!((stats.hands, stats.decisions), (2, 2));
+        assert_eq!(stats.correct, 1 + grade.correct as u32);
+        assert_eq!(stats.total_ev_loss, round(loss));
+        assert!(game.history().is_empty());
+
+        trainer_reset(&mut state);
+        assert_eq!(trainer_stats(&state).decisions, 0);
+        assert!(trainer_answer(&mut state, &mut game, 0).is_err());
+    }
+
+    #[test]
+    fn hand_ends_when_hero_leaves_the_strategy() {
+        let mut game = build_game("Ts7h2cKc5d", RANGES);
+        // OOP always bets half pot, and IP always bets half pot after a check
+        let num_hands = [0, 1].map(|player| game.private_cards(player).len());
+        let mut locked = vec![0.0; game.available_actions().len() * num_hands[0]];
+        locked[num_hands[0]..2 * num_hands[0]].fill(1.0);
+        game.lock_current_strategy(&locked);
+        game.play(0);
+        let mut locked = vec![0.0; game.available_actions().len() * num_hands[1]];
+        locked[num_hands[1]..2 * num_hands[1]].fill(1.0);
+        game.lock_current_strategy(&locked);
+        game.back_to_root();
+        solve(&mut game, 1000, 0.1, false);
+
+        let mut state = TrainerState::default();
+        trainer_start(&mut state, &mut game, &[], Some(0), None, Some(0)).unwrap();
+        let response = trainer_answer(&mut state, &mut game, 0).unwrap();
+        // checking is never in the strategy, so hero has no reach facing the bet
+        assert_eq!(response.grade.as_ref().unwrap().strategy[0], 0.0);
+        assert!(response.question.is_none());
+        assert!(response.message.is_some() && response.villain_hand.is_some());
+        assert!(trainer_answer(&mut state, &mut game, 0).is_err());
+        assert_eq!(trainer_stats(&state).decisions, 1);
+    }
+}
@@ -0,0 +1,215 @@
+use crate::solver::round;
+use crate::tree::encode_action;
+
+use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TranslationMethod {
+    /// Randomizes between the two neighboring sizes (Ganzfried and Sandholm, 2013).
+    #[default]
+    PseudoHarmonic,
+    /// Always picks the size closest in pot fraction.
+    Nearest,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslatedAction {
+    pub index: usize,
+    pub action: String,
+    pub probability: f64,
+}
+
+/// Returns the amount each player had put in when the current street started.
+fn street_start_amount(game: &mut PostFlopGame) -> i32 {
+    let history = game.history().to_vec();
+    let mut amount = 0;
+    game.back_to_root();
+    for &index in &history {
+        if game.is_chance_node() {
+            amount = game.total_bet_amount()[0];
+        }
+        game.play(index);
+    }
+    amount
+}
+
+/// Probability of mapping `x` to `a` rather than `b`, with all sizes as fractions of the pot.
+#[inline]
+fn pseudo_harmonic(a: f64, b: f64, x: f64) -> f64 {
+    (b - x) * (1.0 + a) / ((b - a) * (1.0 + x))
+}
+
+/// Maps a bet or raise to `amount` (in chips put in on the current street) onto the aggressive
+/// actions available at the current node. Sizes are compared as fractions of the pot after calling.
+/// A size below the smallest one is mapped to it or to checking/calling.
+pub fn translate_bet(
+    game: &mut PostFlopGame,
+    amount: i32,
+    method: TranslationMethod,
+) -> Result<Vec<TranslatedAction>, String> {
+    let total = game.total_bet_amount();
+    let max_total = total[0].max(total[1]);
+    let call_amount = max_total - street_start_amount(game);
+    if amount <= call_amount {
+        return Err(format!("Amount must exceed the current bet: {call_amount}"));
+    }
+
+    let pot = (game.tree_config().starting_pot + 2 * max_total) as f64;
+    let fraction = |to: i32| (to - call_amount) as f64 / pot;
+
+    let actions = game.available_actions();
+    let candidates = actions
+        .iter()
+        .cloned()
+        .enumerate()
+        .filter_map(|(i, action)| match action {
+            Action::Bet(to) | Action::Raise(to) | Action::AllIn(to) => Some((i, action, to)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Err("No bet or raise is available".to_string());
+    }
+
+    let single = |(index, action, _): (usize, Action, i32)| {
+        vec![TranslatedAction {
+            index,
+            action: encode_action(action),
+            probability: 1.0,
+        }]
+    };
+
+    // checking or calling is the size-zero neighbor of bets below the smallest size
+    let passive = actions
+        .iter()
+        .position(|&a| a == Action::Check || a == Action::Call)
+        .map(|i| (i, actions[i], call_amount));
+
+    let x = fraction(amount);
+    let upper = candidates.iter().position(|&(_, _, to)| to >= amount);
+    let (lower, upper) = match upper {
+        None => return Ok(single(*candidates.last().unwrap())),
+        Some(i) if candidates[i].2 == amount => return Ok(single(candidates[i])),
+        Some(0) => match passive {
+            Some(passive) => (passive, candidates[0]),
+            None => return Ok(single(candidates[0])),
+        },
+        Some(i) => (candidates[i - 1], candidates[i]),
+    };
+
+    let (a, b) = (fraction(lower.2), fraction(upper.2));
+    let probability = match method {
+        TranslationMethod::PseudoHarmonic => pseudo_harmonic(a, b, x),
+        TranslationMethod::Nearest => ((x - a) <= (b - x)) as i32 as f64,
+    };
+
+    Ok([(lower, probability), (upper, 1.0 - probability)]
+        .into_iter()
+        .filter(|&(_, p)| p > 0.0)
+        .map(|((index, action, _), p)| TranslatedAction {
+            index,
+            action: encode_action(action),
+            probability: round(p),
+        })
+        .collect())
+}
+
+/// Translates an off-tree bet or raise at the current node of the game.
+pub fn game_translate_action(
+    game_state: &mut PostFlopGame,
+    amount: i32,
+    method: TranslationMethod,
+) -> Result<Vec<TranslatedAction>, String> {
+    if !game_state.is_ready() && !game_state.is_solved() {
+        return Err("Game is not ready".to_string());
+    }
+    if game_state.is_terminal_node() || game_state.is_chance_node() {
+        return Err("Current node is not a decision node".to_string());
+    }
+    translate_bet(game_state, amount, method)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::tests::solved_river_game;
+
+    fn translated(game: &mut PostFlopGame, amount: i32) -> Vec<(String, f64)> {
+        translate_bet(game, amount, TranslationMethod::PseudoHarmonic)
+            .unwrap()
+            .into_iter()
+            .map(|t| {
+                assert_eq!(t.action, encode_action(game.available_actions()[t.index]));
+                (t.action, t.probability)
+            })
+            .collect()
+    }
+
+    fn assert_sums_to_one(result: &[(String, f64)]) {
+        let total = result.iter().map(|(_, p)| p).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-5, "{result:?}");
+    }
+
+    #[test]
+    fn pseudo_harmonic_matches_the_neighbors() {
+        assert_eq!(pseudo_harmonic(0.5, 1.0, 0.5), 1.0);
+        assert_eq!(pseudo_harmonic(0.5, 1.0, 1.0), 0.0);
+        // a bet of 0.8 pot is closer to pot than to half pot in the harmonic sense
+        assert!((pseudo_harmonic(0.5, 1.0, 0.8) - 1.0 / 3.0).abs() < 1e-9);
+        assert!((pseudo_harmonic(0.0, 0.5, 0.2) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn translate_bet_on_tree_and_between_sizes() {
+        // the root has a check and bets of 50 and 100 into 100
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        assert_eq!(translated(&mut game, 50), [("B50".to_string(), 1.0)]);
+        assert_eq!(translated(&mut game, 100), [("B100".to_string(), 1.0)]);
+
+        let result = translated(&mut game, 80);
+        assert_eq!(result[0].0, "B50");
+        assert_eq!(result[1].0, "B100");
+        assert_eq!(result[0].1, round(1.0 / 3.0));
+        assert_sums_to_one(&result);
+    }
+
+    #[test]
+    fn translate_bet_below_the_smallest_size_mixes_with_check_or_call() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        let result = translated(&mut game, 20);
+        assert_eq!(result[0], ("X".to_string(), 0.5));
+        assert_eq!(result[1], ("B50".to_string(), 0.5));
+
+        // facing a bet of 50, the only raise is all-in, so a raise to 200 mixes with calling
+        let bet = game
+            .available_actions()
+            .iter()
+            .position(|&a| a == Action::Bet(50));
+        game.play(bet.unwrap());
+        let result = translated(&mut game, 200);
+        assert_eq!(result[0].0, "C");
+        assert_eq!(result[1].0, encode_action(Action::AllIn(900)));
+        assert_eq!(result[0].1, round(3.5 / (4.25 * 1.75)));
+        assert_sums_to_one(&result);
+    }
+
+    #[test]
+    fn translate_bet_above_the_allin_picks_the_allin() {
+        let mut game = solved_river_game(["AK,QQ,76s", "KK-TT,A5s,K9s"]);
+        // without an all-in at the root, a bet above the largest size maps to it
+        assert_eq!(translated(&mut game, 300), [("B100".to_string(), 1.0)]);
+
+        let bet = game
+            .available_actions()
+            .iter()
+            .position(|&a| a == Action::Bet(50));
+        game.play(bet.unwrap());
+        let allin = encode_action(Action::AllIn(900));
+        assert_eq!(translated(&mut game, 900), [(allin.clone(), 1.0)]);
+        assert_eq!(translated(&mut game, 1000), [(allin, 1.0)]);
+        assert!(translate_bet(&mut game, 50, TranslationMethod::PseudoHarmonic).is_err());
+    }
+}
@@ -1,4 +1,50 @@
+use crate::config::GameConfig;
+
 use postflop_solver::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Street {
+    Flop,
+    Turn,
+    River,
+}
+
+impl Street {
+    pub fn from_board_len(len: usize) -> Self {
+        match len {
+            3 => Street::Flop,
+            4 => Street::Turn,
+            _ => Street::River,
+        }
+    }
+
+    pub fn from_board_state(state: BoardState) -> Self {
+        match state {
+            BoardState::Flop => Street::Flop,
+            BoardState::Turn => Street::Turn,
+            BoardState::River => Street::River,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Street::Flop => Street::Turn,
+            _ => Street::River,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Street::Flop => "flop",
+            Street::Turn => "turn",
+            Street::River => "river",
+        }
+    }
+}
 
 #[inline]
 fn action_to_string(action: Action) -> String {
@@ -14,7 +60,7 @@ fn action_to_string(action: Action) -> String {
 }
 
 #[inline]
-fn encode_action(action: Action) -> String {
+pub fn encode_action(action: Action) -> String {
     match action {
         Action::Fold => "F".to_string(),
         Action::Check => "X".to_string(),
@@ -26,7 +72,9 @@ fn encode_action(action: Action) -> String {
     }
 }
 
-fn encode_line(line: &[Action]) -> String {
+/// Encodes a line such as `X-B30-C|B60`. Dealt cards given as `Action::Chance` are written
+/// between `|`, as in `X-B30-C|Td|B60`.
+pub fn encode_line(line: &[Action]) -> String {
     let mut flag = 0;
     let mut encoded = String::new();
 
@@ -35,42 +83,56 @@ fn encode_line(line: &[Action]) -> String {
     }
 
     for &action in line {
+        let is_chance = matches!(action, Action::Chance(_));
         if !encoded.is_empty() {
-            let delimiter = if flag == 2 { "|" } else { "-" };
+            let delimiter = if is_chance || flag == 2 { "|" } else { "-" };
             flag = if flag == 2 { 0 } else { flag };
             encoded.push_str(delimiter);
         }
         match action {
             Action::Check => flag += 1,
-            Action::Call => flag = 2,
+            Action::Call | Action::Chance(_) => flag = 2,
             _ => flag = 0,
         }
-        encoded.push_str(&encode_action(action));
+        match action {
+            Action::Chance(card) => encoded.push_str(&card_to_string(card).unwrap()),
+            _ => encoded.push_str(&encode_action(action)),
+        }
     }
 
     encoded
 }
 
-#[inline]
-fn decode_action(action: &str) -> Action {
+pub fn try_decode_action(action: &str) -> Option<Action> {
     match action {
-        "F" => Action::Fold,
-        "X" => Action::Check,
-        "C" => Action::Call,
+        "F" => Some(Action::Fold),
+        "X" => Some(Action::Check),
+        "C" => Some(Action::Call),
         _ => {
             let mut chars = action.chars();
-            let first_char = chars.next().unwrap();
-            let amount = chars.as_str().parse().unwrap();
+            let first_char = chars.next()?;
+            let amount = chars.as_str().parse().ok()?;
             match first_char {
-                'B' => Action::Bet(amount),
-                'R' => Action::Raise(amount),
-                'A' => Action::AllIn(amount),
-                _ => unreachable!(),
+                'B' => Some(Action::Bet(amount)),
+                'R' => Some(Action::Raise(amount)),
+                'A' => Some(Action::AllIn(amount)),
+                _ => None,
             }
         }
     }
 }
 
+#[inline]
+fn decode_action(action: &str) -> Action {
+    try_decode_action(action).unwrap()
+}
+
+pub fn decode_line(line: &str) -> Result<Vec<Action>, String> {
+    line.split(&['-', '|'][..])
+        .map(|action| try_decode_action(action).ok_or_else(|| format!("Invalid line: {line}")))
+        .collect()
+}
+
 pub fn default_action_tree() -> ActionTree {
     let tree_config = TreeConfig {
         starting_pot: 1,
@@ -80,113 +142,36 @@ pub fn default_action_tree() -> ActionTree {
     ActionTree::new(tree_config).unwrap()
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn tree_new(
-    tree_state: &mut ActionTree,
-    board_len: i32,
-    starting_pot: i32,
-    effective_stack: i32,
-    donk_option: bool,
-    oop_flop_bet: String,
-    oop_flop_raise: String,
-    oop_turn_bet: String,
-    oop_turn_raise: String,
-    oop_turn_donk: String,
-    oop_river_bet: String,
-    oop_river_raise: String,
-    oop_river_donk: String,
-    ip_flop_bet: String,
-    ip_flop_raise: String,
-    ip_turn_bet: String,
-    ip_turn_raise: String,
-    ip_river_bet: String,
-    ip_river_raise: String,
-    add_allin_threshold: f64,
-    force_allin_threshold: f64,
-    merging_threshold: f64,
-    added_lines: String,
-    removed_lines: String,
-) -> bool {
-    let initial_state = match board_len {
-        len if len <= 3 => BoardState::Flop,
-        4 => BoardState::Turn,
-        5 => BoardState::River,
-        _ => panic!("Invalid board length"),
-    };
-
-    let config = TreeConfig {
-        initial_state,
-        starting_pot,
-        effective_stack,
-        rake_rate: 0.0,
-        rake_cap: 0.0,
-        flop_bet_sizes: [
-            BetSizeOptions::try_from((oop_flop_bet.as_str(), oop_flop_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_flop_bet.as_str(), ip_flop_raise.as_str())).unwrap(),
-        ],
-        turn_bet_sizes: [
-            BetSizeOptions::try_from((oop_turn_bet.as_str(), oop_turn_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_turn_bet.as_str(), ip_turn_raise.as_str())).unwrap(),
-        ],
-        river_bet_sizes: [
-            BetSizeOptions::try_from((oop_river_bet.as_str(), oop_river_raise.as_str())).unwrap(),
-            BetSizeOptions::try_from((ip_river_bet.as_str(), ip_river_raise.as_str())).unwrap(),
-        ],
-        turn_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_turn_donk.as_str()).ok(),
-        },
-        river_donk_sizes: match donk_option {
-            false => None,
-            true => DonkSizeOptions::try_from(oop_river_donk.as_str()).ok(),
-        },
-        add_allin_threshold,
-        force_allin_threshold,
-        merging_threshold,
+/// Builds the tree as it is solved, with the tree-building options applied on top of the edited
+/// lines.
+pub fn tree_new(tree_state: &mut ActionTree, origin: &mut TreeOrigin, config: &GameConfig) -> bool {
+    let Ok(mut tree) = config.action_tree() else {
+        return false;
     };
-
-    let mut tree = ActionTree::new(config).unwrap();
-
-    if !added_lines.is_empty() {
-        for line in added_lines.split(',') {
-            let line = line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if tree.add_line(&line).is_err() {
-                return false;
-            }
-        }
-    }
-
-    if !removed_lines.is_empty() {
-        for line in removed_lines.split(',') {
-            let line = line
-                .split(&['-', '|'][..])
-                .map(decode_action)
-                .collect::<Vec<_>>();
-            if tree.remove_line(&line).is_err() {
-                return false;
-            }
-        }
+    let user = TreeSnapshot::new(&tree);
+    if apply_tree_options(&mut tree, config.max_raises, config.bet_unit).is_err() {
+        return false;
     }
-
+    *origin = TreeOrigin {
+        user,
+        built: TreeSnapshot::new(&tree),
+    };
     *tree_state = tree;
     true
 }
 
-pub fn tree_added_lines(tree_state: &ActionTree) -> String {
-    tree_state
-        .added_lines()
+pub fn tree_added_lines(tree_state: &ActionTree, origin: &TreeOrigin) -> String {
+    let lines = origin.user_lines(|s| &s.added_lines, tree_state.added_lines());
+    lines
         .iter()
         .map(|l| encode_line(l))
         .collect::<Vec<_>>()
         .join(",")
 }
 
-pub fn tree_removed_lines(tree_state: &ActionTree) -> String {
-    tree_state
-        .removed_lines()
+pub fn tree_removed_lines(tree_state: &ActionTree, origin: &TreeOrigin) -> String {
+    let lines = origin.user_lines(|s| &s.removed_lines, tree_state.removed_lines());
+    lines
         .iter()
         .map(|l| encode_line(l))
         .collect::<Vec<_>>()
@@ -273,3 +258,1282 @@ pub fn tree_delete_removed_line(tree_state: &mut ActionTree, line: String) {
         .collect::<Vec<_>>();
     tree_state.add_line(&line).unwrap();
 }
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeType {
+    Decision,
+    Chance,
+    Terminal,
+}
+
+pub struct TreeNode<'a> {
+    pub line: &'a [Action],
+    pub node_type: NodeType,
+    pub street: Street,
+    pub player: usize,
+}
+
+/// Visits every node of the tree in depth-first order, restoring the current node afterwards.
+/// A chance node shares its line with the first decision node of the next street, and is visited
+/// right before it.
+pub fn walk_tree(tree_state: &mut ActionTree, f: &mut impl FnMut(&ActionTree, &TreeNode)) {
+    let history = tree_state.history().to_vec();
+    let street = Street::from_board_state(tree_state.config().initial_state);
+    let mut line = Vec::new();
+    tree_state.back_to_root();
+    walk_tree_recursive(tree_state, &mut line, street, 0, f);
+    tree_state.apply_history(&history).unwrap();
+}
+
+fn walk_tree_recursive(
+    tree_state: &mut ActionTree,
+    line: &mut Vec<Action>,
+    mut street: Street,
+    mut player: usize,
+    f: &mut impl FnMut(&ActionTree, &TreeNode),
+) {
+    let mut visit = |tree_state: &ActionTree, line: &[Action], node_type, street, player| {
+        let node = TreeNode {
+            line,
+            node_type,
+            street,
+            player,
+        };
+        f(tree_state, &node);
+    };
+
+    if tree_state.is_terminal_node() {
+        visit(tree_state, line, NodeType::Terminal, street, player);
+        return;
+    }
+
+    if tree_state.is_chance_node() {
+        street = street.next();
+        player = 0;
+        visit(tree_state, line, NodeType::Chance, street, player);
+    }
+
+    visit(tree_state, line, NodeType::Decision, street, player);
+
+    for action in tree_state.available_actions().to_vec() {
+        line.push(action);
+        tree_state.play(action).unwrap();
+        walk_tree_recursive(tree_state, line, street, player ^ 1, f);
+        line.pop();
+        tree_state.apply_history(line).unwrap();
+    }
+}
+
+#[derive(Default, Serialize)]
+pub struct NodeCounts {
+    decision: usize,
+    chance: usize,
+    terminal: usize,
+}
+
+impl NodeCounts {
+    fn add(&mut self, node_type: NodeType) {
+        match node_type {
+            NodeType::Decision => self.decision += 1,
+            NodeType::Chance => self.chance += 1,
+            NodeType::Terminal => self.terminal += 1,
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+pub struct TreeStatsResponse {
+    total: NodeCounts,
+    per_street: [NodeCounts; 3],
+    per_player: [usize; 2],
+    max_depth: usize,
+    allin_terminals: usize,
+    widest_lines: Vec<(String, usize)>,
+}
+
+const NUM_WIDEST_LINES: usize = 10;
+
+pub fn tree_stats(tree_state: &mut ActionTree) -> TreeStatsResponse {
+    let effective_stack = tree_state.config().effective_stack;
+    let mut stats = TreeStatsResponse::default();
+    let mut widest_lines = Vec::<(usize, Vec<Action>)>::new();
+
+    walk_tree(tree_state, &mut |tree, node| {
+        stats.total.add(node.node_type);
+        stats.per_street[node.street as usize].add(node.node_type);
+        stats.max_depth = stats.max_depth.max(node.line.len());
+
+        match node.node_type {
+            NodeType::Decision => {
+                stats.per_player[node.player] += 1;
+                let num_actions = tree.available_actions().len();
+                if widest_lines.len() < NUM_WIDEST_LINES
+                    || widest_lines.last().unwrap().0 < num_actions
+                {
+                    let index = widest_lines.partition_point(|&(n, _)| n >= num_actions);
+                    widest_lines.insert(index, (num_actions, node.line.to_vec()));
+                    widest_lines.truncate(NUM_WIDEST_LINES);
+                }
+            }
+            NodeType::Terminal => {
+                let is_fold = node.line.last() == Some(&Action::Fold);
+                if !is_fold && tree.total_bet_amount() == [effective_stack; 2] {
+                    stats.allin_terminals += 1;
+                }
+            }
+            NodeType::Chance => {}
+        }
+    });
+
+    stats.widest_lines = widest_lines
+        .into_iter()
+        .map(|(n, line)| (encode_line(&line), n))
+        .collect();
+    stats
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TreeExportFormat {
+    Dot,
+    Json,
+}
+
+impl TreeExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            TreeExportFormat::Dot => "text/vnd.graphviz",
+            TreeExportFormat::Json => "application/json",
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeExportNode {
+    action: Option<String>,
+    line: String,
+    node_type: NodeType,
+    street: Street,
+    player: Option<usize>,
+    pot: i32,
+    bet_amount: [i32; 2],
+    is_added: bool,
+    is_invalid: bool,
+    removed_actions: Vec<String>,
+    children: Vec<TreeExportNode>,
+}
+
+struct EditedLines {
+    added: HashSet<Vec<Action>>,
+    removed: Vec<Vec<Action>>,
+    invalid: HashSet<Vec<Action>>,
+}
+
+fn build_export_node(
+    tree_state: &mut ActionTree,
+    edited: &EditedLines,
+    line: &mut Vec<Action>,
+    mut street: Street,
+    mut player: usize,
+) -> TreeExportNode {
+    let bet_amount = tree_state.total_bet_amount();
+    let is_terminal = tree_state.is_terminal_node();
+    let node_type = if is_terminal {
+        NodeType::Terminal
+    } else if tree_state.is_chance_node() {
+        street = street.next();
+        player = 0;
+        NodeType::Chance
+    } else {
+        NodeType::Decision
+    };
+
+    let removed_actions = edited
+        .removed
+        .iter()
+        .filter(|l| l.len() == line.len() + 1 && l.starts_with(line))
+        .map(|l| action_to_string(*l.last().unwrap()))
+        .collect();
+
+    let mut children = Vec::new();
+    if !is_terminal {
+        for action in tree_state.available_actions().to_vec() {
+            line.push(action);
+            tree_state.play(action).unwrap();
+            children.push(build_export_node(
+                tree_state,
+                edited,
+                line,
+                street,
+                player ^ 1,
+            ));
+            line.pop();
+            tree_state.apply_history(line).unwrap();
+        }
+    }
+
+    TreeExportNode {
+        action: line.last().map(|&a| action_to_string(a)),
+        line: encode_line(line),
+        node_type,
+        street,
+        player: (!is_terminal).then_some(player),
+        pot: tree_state.config().starting_pot + bet_amount[0] + bet_amount[1],
+        bet_amount,
+        is_added: edited.added.contains(line.as_slice()),
+        is_invalid: edited.invalid.contains(line.as_slice()),
+        removed_actions,
+        children,
+    }
+}
+
+fn write_dot_node(out: &mut String, node: &TreeExportNode, id: &mut usize) -> usize {
+    let node_id = *id;
+    *id += 1;
+
+    let label = match node.node_type {
+        NodeType::Terminal => "Terminal".to_string(),
+        NodeType::Chance => format!("Chance ({})", node.street.as_str()),
+        NodeType::Decision => {
+            let player = if node.player == Some(0) { "OOP" } else { "IP" };
+            format!("{player} ({})", node.street.as_str())
+        }
+    };
+    let style = match (node.is_invalid, node.node_type) {
+        (true, _) => ", style=filled, fillcolor=\"#fca5a5\"",
+        (false, NodeType::Terminal) => ", style=filled, fillcolor=\"#e5e7eb\"",
+        (false, NodeType::Chance) => ", shape=diamond",
+        _ => "",
+    };
+    writeln!(
+        out,
+        "  n{node_id} [label=\"{label}\\npot {}\\nbets {} / {}\"{style}];",
+        node.pot, node.bet_amount[0], node.bet_amount[1]
+    )
+    .unwrap();
+
+    for child in &node.children {
+        let child_id = write_dot_node(out, child, id);
+        let color = if child.is_added {
+            ", color=\"#16a34a\", fontcolor=\"#16a34a\", penwidth=2"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "  n{node_id} -> n{child_id} [label=\"{}\"{color}];",
+            child.action.as_deref().unwrap_or_default()
+        )
+        .unwrap();
+    }
+
+    for action in &node.removed_actions {
+        let removed_id = *id;
+        *id += 1;
+        writeln!(
+            out,
+            "  n{removed_id} [label=\"Removed\", style=dashed, color=\"#dc2626\"];"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  n{node_id} -> n{removed_id} [label=\"{action}\", style=dashed, color=\"#dc2626\", fontcolor=\"#dc2626\"];"
+        )
+        .unwrap();
+    }
+
+    node_id
+}
+
+/// Exports the whole tree, marking added lines, removed lines (as children that no longer exist)
+/// and invalid terminals.
+pub fn tree_export(tree_state: &mut ActionTree, format: TreeExportFormat) -> String {
+    let edited = EditedLines {
+        added: tree_state.added_lines().iter().cloned().collect(),
+        removed: tree_state.removed_lines().to_vec(),
+        invalid: tree_state.invalid_terminals().iter().cloned().collect(),
+    };
+
+    let history = tree_state.history().to_vec();
+    let street = Street::from_board_state(tree_state.config().initial_state);
+    tree_state.back_to_root();
+    let root = build_export_node(tree_state, &edited, &mut Vec::new(), street, 0);
+    tree_state.apply_history(&history).unwrap();
+
+    match format {
+        TreeExportFormat::Json => serde_json::to_string(&root).unwrap(),
+        TreeExportFormat::Dot => {
+            let mut out = String::from("digraph ActionTree {\n  node [shape=box];\n");
+            write_dot_node(&mut out, &root, &mut 0);
+            out.push_str("}\n");
+            out
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedNode {
+    line: String,
+    base_actions: Vec<String>,
+    other_actions: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeDiffResponse {
+    only_in_base: Vec<String>,
+    only_in_other: Vec<String>,
+    changed_nodes: Vec<ChangedNode>,
+}
+
+fn collect_nodes(tree: &mut ActionTree) -> HashMap<Vec<Action>, Vec<Action>> {
+    let mut nodes = HashMap::new();
+    walk_tree(tree, &mut |tree, node| {
+        let actions = match node.node_type {
+            NodeType::Terminal => Vec::new(),
+            _ => tree.available_actions().to_vec(),
+        };
+        nodes.insert(node.line.to_vec(), actions);
+    });
+    nodes
+}
+
+/// Lines of `a` that are missing in `b`, reported only at the point where the trees diverge.
+fn lines_only_in(
+    a: &HashMap<Vec<Action>, Vec<Action>>,
+    b: &HashMap<Vec<Action>, Vec<Action>>,
+) -> Vec<String> {
+    let mut lines = a
+        .keys()
+        .filter(|line| !b.contains_key(*line) && b.contains_key(&line[..line.len() - 1]))
+        .collect::<Vec<_>>();
+    lines.sort();
+    lines.into_iter().map(|line| encode_line(line)).collect()
+}
+
+pub fn tree_diff(mut base: ActionTree, mut other: ActionTree) -> TreeDiffResponse {
+    let base_nodes = collect_nodes(&mut base);
+    let other_nodes = collect_nodes(&mut other);
+
+    let mut changed = base_nodes
+        .iter()
+        .filter_map(|(line, base_actions)| {
+            let other_actions = other_nodes.get(line)?;
+            (base_actions != other_actions).then_some((line, base_actions, other_actions))
+        })
+        .collect::<Vec<_>>();
+    changed.sort_by(|a, b| a.0.cmp(b.0));
+
+    let encode_actions = |actions: &[Action]| actions.iter().cloned().map(encode_action).collect();
+
+    TreeDiffResponse {
+        only_in_base: lines_only_in(&base_nodes, &other_nodes),
+        only_in_other: lines_only_in(&other_nodes, &base_nodes),
+        changed_nodes: changed
+            .into_iter()
+            .map(|(line, base_actions, other_actions)| ChangedNode {
+                line: encode_line(line),
+                base_actions: encode_actions(base_actions),
+                other_actions: encode_actions(other_actions),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum TreeEditRule {
+    /// Removes every raise (all-in raises are kept).
+    RemoveRaises { street: Option<Street> },
+    /// Removes raises and all-in raises once `max_raises` raises have been made on the street.
+    CapRaises {
+        street: Option<Street>,
+        max_raises: usize,
+    },
+    /// Removes OOP leads on streets where OOP called the last bet of the previous street.
+    RemoveDonkBets { street: Option<Street> },
+    /// Replaces bets and raises that are not a multiple of `unit` with the nearest multiple.
+    RoundBets { street: Option<Street>, unit: i32 },
+    /// Adds a bet of `size` percent of the pot wherever `player` is not facing a bet.
+    AddBet {
+        street: Option<Street>,
+        player: usize,
+        size: f64,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeEditResponse {
+    edit_added_lines: Vec<String>,
+    edit_removed_lines: Vec<String>,
+    added_lines: String,
+    removed_lines: String,
+    invalid_terminals: String,
+}
+
+/// Splits `line` into streets. The last element is the (possibly empty) current street.
+fn split_streets(line: &[Action]) -> Vec<&[Action]> {
+    let mut streets = Vec::new();
+    let mut start = 0;
+    for (i, &action) in line.iter().enumerate() {
+        let is_check_back = action == Action::Check && i > start;
+        if action == Action::Call || is_check_back {
+            streets.push(&line[start..=i]);
+            start = i + 1;
+        }
+    }
+    streets.push(&line[start..]);
+    streets
+}
+
+fn is_aggressive(action: Action) -> bool {
+    matches!(action, Action::Bet(_) | Action::Raise(_) | Action::AllIn(_))
+}
+
+fn rebuild_tree(tree_state: &ActionTree) -> Result<ActionTree, String> {
+    tree_from_snapshot(tree_state.config(), &TreeSnapshot::new(tree_state))
+}
+
+fn tree_from_snapshot(config: &TreeConfig, snapshot: &TreeSnapshot) -> Result<ActionTree, String> {
+    let mut tree = ActionTree::new(config.clone())?;
+    for line in &snapshot.added_lines {
+        tree.add_line(line)?;
+    }
+    for line in &snapshot.removed_lines {
+        tree.remove_line(line)?;
+    }
+    Ok(tree)
+}
+
+fn collect_rule_edits(
+    tree_state: &mut ActionTree,
+    rules: &[TreeEditRule],
+) -> (Vec<Vec<Action>>, Vec<Vec<Action>>) {
+    let config = tree_state.config().clone();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+
+    walk_tree(tree_state, &mut |tree, node| {
+        if node.node_type != NodeType::Decision {
+            return;
+        }
+
+        let streets = split_streets(node.line);
+        let current = *streets.last().unwrap();
+        let is_facing_bet = current.iter().any(|&a| is_aggressive(a));
+        let num_raises = current
+            .iter()
+            .filter(|&&a| is_aggressive(a))
+            .count()
+            .saturating_sub(1);
+        let actions = tree.available_actions();
+        let mut remove = |pred: &dyn Fn(Action) -> bool| {
+            for &action in actions.iter().filter(|&&a| pred(a)) {
+                removed.push([node.line, &[action]].concat());
+            }
+        };
+
+        for rule in rules {
+            match *rule {
+                TreeEditRule::RemoveRaises { street } => {
+                    if street.is_none_or(|s| s == node.street) {
+                        remove(&|a| matches!(a, Action::Raise(_)));
+                    }
+                }
+                TreeEditRule::CapRaises { street, max_raises } => {
+                    if street.is_none_or(|s| s == node.street)
+                        && is_facing_bet
+                        && num_raises >= max_raises
+                    {
+                        remove(&|a| matches!(a, Action::Raise(_) | Action::AllIn(_)));
+                    }
+                }
+                TreeEditRule::RemoveDonkBets { street } => {
+                    let prev = streets.len().checked_sub(2).map(|i| streets[i]);
+                    let oop_called = prev.is_some_and(|prev| {
+                        prev.last() == Some(&Action::Call) && prev.len() % 2 == 1
+                    });
+                    if street.is_none_or(|s| s == node.street) && current.is_empty() && oop_called {
+                        remove(&|a| matches!(a, Action::Bet(_) | Action::AllIn(_)));
+                    }
+                }
+                TreeEditRule::RoundBets { street, unit } => {
+                    if street.is_some_and(|s| s != node.street) || unit <= 1 {
+                        continue;
+                    }
+                    // the street amount of the last aggressor tells the contributions before this
+                    // street
+                    let bet_amount = tree.total_bet_amount();
+                    let start = match current.iter().rposition(|&a| is_aggressive(a)) {
+                        Some(i) => match current[i] {
+                            Action::Bet(a) | Action::Raise(a) | Action::AllIn(a) => {
+                                bet_amount[i % 2] - a
+                            }
+                            _ => unreachable!(),
+                        },
+                        None => bet_amount[0],
+                    };
+                    let max_amount = config.effective_stack - start;
+                    for &action in actions {
+                        // raises are rounded up so that they stay above the minimum raise
+                        let (rounded, amount) = match action {
+                            Action::Bet(amount) => {
+                                let rounded = (amount as f64 / unit as f64).round() as i32 * unit;
+                                (Action::Bet(rounded.max(unit)), amount)
+                            }
+                            Action::Raise(amount) => {
+                                let rounded = (amount + unit - 1) / unit * unit;
+                                (Action::Raise(rounded), amount)
+                            }
+                            _ => continue,
+                        };
+                        if amount % unit == 0 {
+                            continue;
+                        }
+                        remove(&|a| a == action);
+                        let (Action::Bet(rounded_amount) | Action::Raise(rounded_amount)) = rounded
+                        else {
+                            unreachable!()
+                        };
+                        // sizes rounded to the stack or beyond become an all-in
+                        let (rounded, rounded_amount) = match rounded_amount < max_amount {
+                            true => (rounded, rounded_amount),
+                            false => (Action::AllIn(max_amount), max_amount),
+                        };
+                        let exists = actions.iter().any(|&a| {
+                            matches!(a, Action::Bet(x) | Action::Raise(x) | Action::AllIn(x)
+                                if x == rounded_amount)
+                        });
+                        if !exists {
+                            added.push([node.line, &[rounded]].concat());
+                        }
+                    }
+                }
+                TreeEditRule::AddBet {
+                    street,
+                    player,
+                    size,
+                } => {
+                    if street.is_some_and(|s| s != node.street)
+                        || node.player != player
+                        || is_facing_bet
+                    {
+                        continue;
+                    }
+                    let bet_amount = tree.total_bet_amount();
+                    let pot = config.starting_pot + bet_amount[0] + bet_amount[1];
+                    let amount = (pot as f64 * size / 100.0).round() as i32;
+                    let stack = config.effective_stack - bet_amount[player];
+                    let exists = actions
+                        .iter()
+                        .any(|&a| a == Action::Bet(amount) || a == Action::AllIn(amount));
+                    if amount > 0 && amount < stack && !exists {
+                        added.push([node.line, &[Action::Bet(amount)]].concat());
+                    }
+                }
+            }
+        }
+    });
+
+    // drop lines inside an already removed subtree
+    removed.sort();
+    removed.dedup();
+    let mut pruned: Vec<Vec<Action>> = Vec::new();
+    for line in removed {
+        if !pruned.iter().any(|p| line.starts_with(p)) {
+            pruned.push(line);
+        }
+    }
+    added.sort();
+    added.dedup();
+    added.retain(|line| !pruned.iter().any(|p| line.starts_with(p)));
+
+    (added, pruned)
+}
+
+/// Applies `rules` to the current tree. The resulting tree replaces the current one only if
+/// `commit` is set; otherwise this is a preview.
+pub fn tree_apply_rules(
+    tree_state: &mut ActionTree,
+    origin: &TreeOrigin,
+    rules: &[TreeEditRule],
+    commit: bool,
+) -> Result<TreeEditResponse, String> {
+    let (added, removed) = collect_rule_edits(tree_state, rules);
+
+    let mut tree = rebuild_tree(tree_state)?;
+    for line in &removed {
+        tree.remove_line(line)?;
+    }
+    for line in &added {
+        tree.add_line(line)?;
+    }
+
+    let response = TreeEditResponse {
+        edit_added_lines: added.iter().map(|l| encode_line(l)).collect(),
+        edit_removed_lines: removed.iter().map(|l| encode_line(l)).collect(),
+        added_lines: tree_added_lines(&tree, origin),
+        removed_lines: tree_removed_lines(&tree, origin),
+        invalid_terminals: tree_invalid_terminals(&tree),
+    };
+
+    if commit {
+        *tree_state = tree;
+    }
+
+    Ok(response)
+}
+
+const MAX_OPTION_PASSES: usize = 8;
+
+/// Applies the raise cap and the bet granularity of the tree-building options on top of the
+/// user's lines. A replaced size gets a freshly generated subtree, so this repeats until the tree
+/// no longer changes, and fails if it still changes after `MAX_OPTION_PASSES` passes.
+pub fn apply_tree_options(
+    tree_state: &mut ActionTree,
+    max_raises: Option<usize>,
+    bet_unit: i32,
+) -> Result<(), String> {
+    let mut rules = Vec::new();
+    if let Some(max_raises) = max_raises {
+        rules.push(TreeEditRule::CapRaises {
+            street: None,
+            max_raises,
+        });
+    }
+    if bet_unit > 1 {
+        rules.push(TreeEditRule::RoundBets {
+            street: None,
+            unit: bet_unit,
+        });
+    }
+
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    for _ in 0..MAX_OPTION_PASSES {
+        let (added, removed) = collect_rule_edits(tree_state, &rules);
+        if added.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+        for line in &removed {
+            tree_state.remove_line(line)?;
+        }
+        for line in &added {
+            tree_state.add_line(line)?;
+        }
+    }
+
+    Err("Tree-building options did not converge".to_string())
+}
+
+#[derive(Default, PartialEq)]
+struct TreeSnapshot {
+    added_lines: Vec<Vec<Action>>,
+    removed_lines: Vec<Vec<Action>>,
+}
+
+impl TreeSnapshot {
+    fn new(tree_state: &ActionTree) -> Self {
+        Self {
+            added_lines: tree_state.added_lines().to_vec(),
+            removed_lines: tree_state.removed_lines().to_vec(),
+        }
+    }
+}
+
+/// Lines of the config the current tree was built from (`user`) and of the tree right after the
+/// tree-building options were applied (`built`). The reported lines are the user's lines and the
+/// edits made since, so that saving them does not bake the options into the config.
+#[derive(Default)]
+pub struct TreeOrigin {
+    user: TreeSnapshot,
+    built: TreeSnapshot,
+}
+
+impl TreeOrigin {
+    fn user_lines(
+        &self,
+        lines: impl Fn(&TreeSnapshot) -> &Vec<Vec<Action>>,
+        current: &[Vec<Action>],
+    ) -> Vec<Vec<Action>> {
+        let (user, built) = (lines(&self.user), lines(&self.built));
+        // the user's lines less those edited away since, then the lines the edits brought in
+        let mut result = user
+            .iter()
+            .filter(|line| !built.contains(line) || current.contains(line))
+            .cloned()
+            .collect::<Vec<_>>();
+        for line in current {
+            if !built.contains(line) && !result.contains(line) {
+                result.push(line.clone());
+            }
+        }
+        result
+    }
+}
+
+struct TreeJournalEntry {
+    operation: String,
+    history: Vec<Action>,
+    before: TreeSnapshot,
+    after: TreeSnapshot,
+}
+
+/// Edits made to the current tree since the last `tree_new`. Entries at and after `position`
+/// have been undone and can be redone.
+#[derive(Default)]
+pub struct TreeJournal {
+    entries: Vec<TreeJournalEntry>,
+    position: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeJournalItem {
+    operation: String,
+    line: String,
+    is_undone: bool,
+}
+
+impl TreeJournal {
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.position = 0;
+    }
+
+    /// Runs `f` on the tree and records it as `operation` if it changed the tree.
+    pub fn record<R>(
+        &mut self,
+        tree_state: &mut ActionTree,
+        operation: String,
+        f: impl FnOnce(&mut ActionTree) -> R,
+    ) -> R {
+        let history = tree_state.history().to_vec();
+        let before = TreeSnapshot::new(tree_state);
+        let result = f(tree_state);
+        let after = TreeSnapshot::new(tree_state);
+
+        if before != after {
+            self.entries.truncate(self.position);
+            self.entries.push(TreeJournalEntry {
+                operation,
+                history,
+                before,
+                after,
+            });
+            self.position = self.entries.len();
+        }
+
+        result
+    }
+}
+
+/// Replaces the tree with the one described by `snapshot`, staying at `history` if it still
+/// exists. The tree is left unchanged if the snapshot cannot be rebuilt.
+fn restore_snapshot(
+    tree_state: &mut ActionTree,
+    snapshot: &TreeSnapshot,
+    history: &[Action],
+) -> Result<(), String> {
+    *tree_state = tree_from_snapshot(tree_state.config(), snapshot)?;
+    if tree_state.apply_history(history).is_err() {
+        tree_state.back_to_root();
+    }
+    Ok(())
+}
+
+pub fn tree_undo(tree_state: &mut ActionTree, journal: &mut TreeJournal) -> Result<bool, String> {
+    if journal.position == 0 {
+        return Ok(false);
+    }
+    let entry = &journal.entries[journal.position - 1];
+    restore_snapshot(tree_state, &entry.before, &entry.history)?;
+    journal.position -= 1;
+    Ok(true)
+}
+
+pub fn tree_redo(tree_state: &mut ActionTree, journal: &mut TreeJournal) -> Result<bool, String> {
+    if journal.position == journal.entries.len() {
+        return Ok(false);
+    }
+    let entry = &journal.entries[journal.position];
+    restore_snapshot(tree_state, &entry.after, &entry.history)?;
+    journal.position += 1;
+    Ok(true)
+}
+
+pub fn tree_journal(journal: &TreeJournal) -> Vec<TreeJournalItem> {
+    journal
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| TreeJournalItem {
+            operation: entry.operation.clone(),
+            line: encode_line(&entry.history),
+            is_undone: i >= journal.position,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Action::{Bet, Call, Chance, Check, Raise};
+    use serde_json::json;
+
+    fn sizes(bet: &[BetSize], raise: &[BetSize]) -> [BetSizeOptions; 2] {
+        let options = BetSizeOptions {
+            bet: bet.to_vec(),
+            raise: raise.to_vec(),
+        };
+        [options.clone(), options]
+    }
+
+    /// River-only tree with a pot of 100 and no automatic all-ins or merged sizes.
+    fn river_tree(effective_stack: i32, bet: &[BetSize], raise: &[BetSize]) -> ActionTree {
+        ActionTree::new(TreeConfig {
+            initial_state: BoardState::River,
+            starting_pot: 100,
+            effective_stack,
+            river_bet_sizes: sizes(bet, raise),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Turn and river tree with a pot of 100, a stack of 1000 and one half-pot bet.
+    fn turn_tree() -> ActionTree {
+        let half_pot = [BetSize::PotRelative(0.5)];
+        ActionTree::new(TreeConfig {
+            initial_state: BoardState::Turn,
+            starting_pot: 100,
+            effective_stack: 1000,
+            turn_bet_sizes: sizes(&half_pot, &[]),
+            river_bet_sizes: sizes(&half_pot, &[]),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn counts(counts: &NodeCounts) -> [usize; 3] {
+        [counts.decision, counts.chance, counts.terminal]
+    }
+
+    #[test]
+    fn encode_line_marks_streets_and_dealt_cards() {
+        assert_eq!(encode_line(&[]), "(Root)");
+        assert_eq!(encode_line(&[Check, Check, Bet(60)]), "X-X|B60");
+        assert_eq!(
+            encode_line(&[Check, Bet(30), Call, Chance(33), Bet(60)]),
+            "X-B30-C|Td|B60"
+        );
+        assert_eq!(
+            encode_line(&[Check, Check, Chance(33), Check, Check, Chance(0)]),
+            "X-X|Td|X-X|2c"
+        );
+    }
+
+    #[test]
+    fn decode_line_rejects_malformed_actions() {
+        assert_eq!(
+            decode_line("X-B20|R60").unwrap(),
+            [Check, Bet(20), Raise(60)]
+        );
+        assert!(decode_line("X-Q20").is_err());
+        assert!(decode_line("B").is_err());
+        assert!(decode_line("").is_err());
+    }
+
+    #[test]
+    fn stats_count_nodes_and_allin_terminals() {
+        // X / B50 / A100 for OOP, then for IP after a check; a bet is either folded or called
+        let allin = [BetSize::PotRelative(0.5), BetSize::AllIn];
+        let mut tree = river_tree(100, &allin, &[]);
+        tree.apply_history(&[Check]).unwrap();
+        let stats = tree_stats(&mut tree);
+        assert_eq!(counts(&stats.total), [6, 0, 9]);
+        assert_eq!(counts(&stats.per_street[Street::River as usize]), [6, 0, 9]);
+        assert_eq!(stats.per_player, [3, 3]);
+        assert_eq!(stats.max_depth, 3);
+        assert_eq!(stats.allin_terminals, 2);
+        assert_eq!(
+            stats.widest_lines[..2],
+            [("(Root)".to_string(), 3), ("X".to_string(), 3)]
+        );
+        assert_eq!(stats.widest_lines.len(), 6);
+
+        // the walk leaves the tree where it was
+        assert_eq!(tree.history(), [Check]);
+    }
+
+    #[test]
+    fn stats_count_chance_nodes_on_the_next_street() {
+        // X-X, X-B50-C and B50-C each lead to a river subtree like the one above without all-ins
+        let stats = tree_stats(&mut turn_tree());
+        assert_eq!(counts(&stats.per_street[Street::Turn as usize]), [4, 0, 2]);
+        assert_eq!(
+            counts(&stats.per_street[Street::River as usize]),
+            [12, 3, 15]
+        );
+        assert_eq!(counts(&stats.total), [16, 3, 17]);
+        assert_eq!(stats.per_player, [8, 8]);
+        assert_eq!(stats.max_depth, 6);
+        assert_eq!(stats.allin_terminals, 0);
+    }
+
+    fn find_node<'a>(node: &'a serde_json::Value, line: &str) -> Option<&'a serde_json::Value> {
+        if node["line"] == line {
+            return Some(node);
+        }
+        let children = node["children"].as_array()?;
+        children.iter().find_map(|child| find_node(child, line))
+    }
+
+    #[test]
+    fn export_marks_edited_lines() {
+        let allin = [BetSize::PotRelative(0.5), BetSize::AllIn];
+        let mut tree = river_tree(100, &allin, &[]);
+        tree.add_line(&[Check, Bet(30)]).unwrap();
+        tree.remove_line(&[Bet(50)]).unwrap();
+
+        let json = tree_export(&mut tree, TreeExportFormat::Json);
+        let root = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+        let child_lines = |line| {
+            let node = find_node(&root, line).unwrap();
+            let children = node["children"].as_array().unwrap();
+            children
+                .iter()
+                .map(|c| c["line"].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(child_lines("(Root)"), ["X", "A100"]);
+        assert_eq!(child_lines("X"), ["X-X", "X-B30", "X-B50", "X-A100"]);
+        assert_eq!(root["removedActions"], serde_json::json!(["Bet:50"]));
+        assert_eq!(find_node(&root, "X-B30").unwrap()["isAdded"], true);
+        assert_eq!(find_node(&root, "X-B50").unwrap()["isAdded"], false);
+
+        let terminal = find_node(&root, "A100-C").unwrap();
+        assert_eq!(terminal["nodeType"], "terminal");
+        assert_eq!(terminal["pot"], 300);
+        assert_eq!(terminal["player"], serde_json::Value::Null);
+
+        // 15 nodes joined by 14 edges, and the removed bet as a dashed edge
+        let dot = tree_export(&mut tree, TreeExportFormat::Dot);
+        assert_eq!(dot.matches(" -> ").count(), 15);
+        assert_eq!(dot.matches("label=\"Removed\"").count(), 1);
+        assert!(dot.contains("[label=\"Bet:30\", color=\"#16a34a\""));
+        assert!(dot.contains("[label=\"Bet:50\", style=dashed"));
+        assert!(dot.contains("[label=\"Bet:50\"];"));
+    }
+
+    #[test]
+    fn diff_reports_where_the_trees_diverge() {
+        let base = river_tree(100, &[BetSize::PotRelative(0.5)], &[]);
+        let other_sizes = [BetSize::PotRelative(0.5), BetSize::PotRelative(0.75)];
+        let other = river_tree(100, &other_sizes, &[]);
+
+        let diff = tree_diff(base, other);
+        assert!(diff.only_in_base.is_empty());
+        // the folds and calls below B75 are not listed separately
+        assert_eq!(diff.only_in_other, ["X-B75", "B75"]);
+
+        let changed = diff
+            .changed_nodes
+            .iter()
+            .map(|node| {
+                (
+                    node.line.as_str(),
+                    node.base_actions.join(","),
+                    node.other_actions.join(","),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changed,
+            [
+                ("(Root)", "X,B50".to_string(), "X,B50,B75".to_string()),
+                ("X", "X,B50".to_string(), "X,B50,B75".to_string()),
+            ]
+        );
+    }
+
+    /// Decodes `lines` in the order the edit rules report them.
+    fn sorted_lines(lines: &[&str]) -> Vec<Vec<Action>> {
+        let mut lines = lines
+            .iter()
+            .map(|line| decode_line(line).unwrap())
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn rules_remove_raises() {
+        // B50, then raises to 150 and 450 before IP is all-in
+        let mut tree = river_tree(
+            1000,
+            &[BetSize::PotRelative(0.5)],
+            &[BetSize::PrevBetRelative(3.0)],
+        );
+
+        let rules = [TreeEditRule::RemoveRaises { street: None }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert!(added.is_empty());
+        assert_eq!(removed, sorted_lines(&["X-B50-R150", "B50-R150"]));
+
+        let rules = [TreeEditRule::RemoveRaises {
+            street: Some(Street::Turn),
+        }];
+        assert_eq!(collect_rule_edits(&mut tree, &rules), (vec![], vec![]));
+
+        let rules = [TreeEditRule::CapRaises {
+            street: None,
+            max_raises: 1,
+        }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert!(added.is_empty());
+        assert_eq!(removed, sorted_lines(&["X-B50-R150-R450", "B50-R150-R450"]));
+    }
+
+    #[test]
+    fn rules_remove_donk_bets_only_after_an_oop_call() {
+        // OOP leads into IP after X-B50-C, but not after B50-C where IP called
+        let mut tree = turn_tree();
+        let rules = [TreeEditRule::RemoveDonkBets { street: None }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert!(added.is_empty());
+        assert_eq!(removed, sorted_lines(&["X-B50-C|B100"]));
+    }
+
+    #[test]
+    fn rules_round_and_add_bets() {
+        let mut tree = river_tree(1000, &[BetSize::PotRelative(0.33)], &[]);
+        let rules = [TreeEditRule::RoundBets {
+            street: None,
+            unit: 10,
+        }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert_eq!(added, sorted_lines(&["X-B30", "B30"]));
+        assert_eq!(removed, sorted_lines(&["X-B33", "B33"]));
+
+        let rules = [TreeEditRule::AddBet {
+            street: Some(Street::River),
+            player: 1,
+            size: 75.0,
+        }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert_eq!(added, sorted_lines(&["X-B75"]));
+        assert!(removed.is_empty());
+    }
+
+    #[test]
+    fn rules_are_previewed_before_being_committed() {
+        let mut tree = river_tree(
+            1000,
+            &[BetSize::PotRelative(0.5)],
+            &[BetSize::PrevBetRelative(3.0)],
+        );
+        let rules = [TreeEditRule::RemoveRaises { street: None }];
+
+        let preview = tree_apply_rules(&mut tree, &Default::default(), &rules, false).unwrap();
+        assert_eq!(preview.edit_removed_lines, ["X-B50-R150", "B50-R150"]);
+        assert_eq!(preview.removed_lines, "X-B50-R150,B50-R150");
+        assert!(tree.removed_lines().is_empty());
+
+        tree_apply_rules(&mut tree, &Default::default(), &rules, true).unwrap();
+        assert_eq!(
+            tree_removed_lines(&tree, &Default::default()),
+            "X-B50-R150,B50-R150"
+        );
+    }
+
+    /// Turn and river tree with a pot of 100: half-pot bets on both streets and 2.4x raises on the
+    /// river.
+    fn raise_tree(effective_stack: i32) -> ActionTree {
+        let half_pot = [BetSize::PotRelative(0.5)];
+        ActionTree::new(TreeConfig {
+            initial_state: BoardState::Turn,
+            starting_pot: 100,
+            effective_stack,
+            turn_bet_sizes: sizes(&half_pot, &[]),
+            river_bet_sizes: sizes(&half_pot, &[BetSize::PrevBetRelative(2.4)]),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn round_bets_measures_from_the_last_aggressor() {
+        // after X-B50-C, IP's river bet of 100 brings IP to 150 in total, so the stack left for a
+        // raise is 250; R240 rounds up to it and becomes an all-in
+        let mut tree = raise_tree(300);
+        let rules = [TreeEditRule::RoundBets {
+            street: None,
+            unit: 50,
+        }];
+        let (added, removed) = collect_rule_edits(&mut tree, &rules);
+        assert_eq!(
+            added,
+            sorted_lines(&[
+                "X-X|B50-R150",
+                "X-X|X-B50-R150",
+                "X-B50-C|B100-A250",
+                "X-B50-C|X-B100-A250",
+                "B50-C|B100-A250",
+                "B50-C|X-B100-A250",
+            ])
+        );
+        assert_eq!(
+            removed,
+            sorted_lines(&[
+                "X-X|B50-R120",
+                "X-X|X-B50-R120",
+                "X-B50-C|B100-R240",
+                "X-B50-C|X-B100-R240",
+                "B50-C|B100-R240",
+                "B50-C|X-B100-R240",
+            ])
+        );
+    }
+
+    #[test]
+    fn tree_options_repeat_until_nothing_changes() {
+        // the first pass replaces the raises, the second finds the new subtrees on the grid
+        let mut tree = raise_tree(300);
+        apply_tree_options(&mut tree, None, 50).unwrap();
+        let rules = [TreeEditRule::RoundBets {
+            street: None,
+            unit: 50,
+        }];
+        assert_eq!(collect_rule_edits(&mut tree, &rules), (vec![], vec![]));
+        let mut added_lines = tree.added_lines().to_vec();
+        added_lines.sort();
+        assert_eq!(added_lines.len(), 6);
+        assert!(added_lines.contains(&decode_line("B50-C|X-B100-A250").unwrap()));
+
+        // with one raise allowed, the 3-bets go
+        let mut tree = river_tree(
+            1000,
+            &[BetSize::PotRelative(0.5)],
+            &[BetSize::PrevBetRelative(3.0)],
+        );
+        apply_tree_options(&mut tree, Some(1), 0).unwrap();
+        let mut removed_lines = tree.removed_lines().to_vec();
+        removed_lines.sort();
+        assert_eq!(
+            removed_lines,
+            sorted_lines(&["X-B50-R150-R450", "B50-R150-R450"])
+        );
+        assert!(tree.added_lines().is_empty());
+    }
+
+    #[test]
+    fn editor_tree_applies_the_options_but_reports_the_user_lines() {
+        let sizes = |raise| json!({ "bet": "50%", "raise": raise });
+        let street_sizes = json!({ "flop": sizes(""), "turn": sizes(""), "river": sizes("2.4x") });
+        let config = crate::config::parse_config(json!({
+            "version": 2,
+            "board": "Ah7d2c5s",
+            "startingPot": 100,
+            "effectiveStack": 300,
+            "sizes": [street_sizes, street_sizes],
+            "donk": { "turnEnabled": false, "turn": "", "riverEnabled": false, "river": "" },
+            "addAllinThreshold": 0.0,
+            "forceAllinThreshold": 0.0,
+            "mergingThreshold": 0.0,
+            "betUnit": 50,
+            "addedLines": "X-X|B30",
+        }))
+        .unwrap();
+
+        let mut tree = default_action_tree();
+        let mut origin = TreeOrigin::default();
+        assert!(tree_new(&mut tree, &mut origin, &config));
+        let editor_tree = collect_nodes(&mut tree);
+        assert_eq!(editor_tree, collect_nodes(&mut config.game_tree().unwrap()));
+        assert!(editor_tree.contains_key(&decode_line("B50-C|B100-A250").unwrap()));
+
+        // the rounded B30 stays one of the user's lines, and the options' lines are not listed
+        assert_eq!(tree_added_lines(&tree, &origin), "X-X|B30");
+        assert_eq!(tree_removed_lines(&tree, &origin), "");
+
+        tree.remove_line(&[Bet(50)]).unwrap();
+        assert_eq!(tree_added_lines(&tree, &origin), "X-X|B30");
+        assert_eq!(tree_removed_lines(&tree, &origin), "B50");
+    }
+
+    #[test]
+    fn undo_and_redo_restore_lines_and_position() {
+        let mut tree = river_tree(100, &[BetSize::PotRelative(0.5)], &[]);
+        let mut journal = TreeJournal::default();
+
+        tree.apply_history(&[Check]).unwrap();
+        journal.record(&mut tree, "Add bet 30".to_string(), |tree| {
+            tree_add_bet_action(tree, 30, false)
+        });
+        tree.apply_history(&[Bet(50)]).unwrap();
+        journal.record(&mut tree, "Remove node".to_string(), |tree| {
+            tree_remove_current_node(tree)
+        });
+        // an operation that changes nothing is not recorded
+        journal.record(&mut tree, "Nothing".to_string(), |_| ());
+        assert_eq!(tree_journal(&journal).len(), 2);
+
+        let lines = |tree: &ActionTree| {
+            let origin = TreeOrigin::default();
+            (
+                tree_added_lines(tree, &origin),
+                tree_removed_lines(tree, &origin),
+            )
+        };
+        assert_eq!(lines(&tree), ("X-B30".to_string(), "B50".to_string()));
+
+        assert_eq!(tree_undo(&mut tree, &mut journal), Ok(true));
+        assert_eq!(lines(&tree), ("X-B30".to_string(), String::new()));
+        assert_eq!(tree.history(), [Bet(50)]);
+
+        assert_eq!(tree_undo(&mut tree, &mut journal), Ok(true));
+        assert_eq!(lines(&tree), (String::new(), String::new()));
+        assert_eq!(tree.history(), [Check]);
+        assert_eq!(tree_undo(&mut tree, &mut journal), Ok(false));
+
+        assert_eq!(tree_redo(&mut tree, &mut journal), Ok(true));
+        assert_eq!(lines(&tree), ("X-B30".to_string(), String::new()));
+        assert_eq!(tree.history(), [Check]);
+
+        // B50 no longer exists once its removal is redone, so the tree goes back to the root
+        assert_eq!(tree_redo(&mut tree, &mut journal), Ok(true));
+        assert_eq!(lines(&tree), ("X-B30".to_string(), "B50".to_string()));
+        assert!(tree.history().is_empty());
+        assert_eq!(tree_redo(&mut tree, &mut journal), Ok(false));
+
+        // a new edit after an undo drops the undone entry
+        tree_undo(&mut tree, &mut journal).unwrap();
+        tree.apply_history(&[Check]).unwrap();
+        journal.record(&mut tree, "Add bet 70".to_string(), |tree| {
+            tree_add_bet_action(tree, 70, false)
+        });
+        let operations = tree_journal(&journal)
+            .into_iter()
+            .map(|item| (item.operation, item.line, item.is_undone))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            operations,
+            [
+                ("Add bet 30".to_string(), "X".to_string(), false),
+                ("Add bet 70".to_string(), "X".to_string(), false),
+            ]
+        );
+        assert_eq!(tree_redo(&mut tree, &mut journal), Ok(false));
+    }
+}
@@ -278,16 +278,20 @@ export const gameMemoryUsageBunching = async (): Promise<number> => {
   return await invoke("game_memory_usage_bunching");
 };
 
-export const gameAllocateMemory = async (enableCompression: boolean) => {
-  await invoke("game_allocate_memory", { enableCompression });
+export const gameAllocateMemory = async (
+  enableCompression: boolean
+): Promise<string | null> => {
+  return await invoke("game_allocate_memory", { enableCompression });
 };
 
 export const gameSetBunching = async (): Promise<string | null> => {
   return await invoke("game_set_bunching");
 };
 
-export const gameSolveStep = async (currentIteration: number) => {
-  await invoke("game_solve_step", { currentIteration });
+export const gameSolveStep = async (
+  currentIteration: number
+): Promise<string | null> => {
+  return await invoke("game_solve_step", { currentIteration });
 };
 
 export const game_solve_steps_with_exploitability = async (currentIteration: number, numIterations: number): Promise<number> => {